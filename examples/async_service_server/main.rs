@@ -52,7 +52,7 @@ fn main() {
 
   println!(">>> ros2_service server created");
 
-  let server_stream = server.receive_request_stream().then(|result| async {
+  let server_stream = server.request_stream().then(|result| async {
     match result {
       Ok((req_id, req)) => {
         println!("request: {} + {}", req.a, req.b);
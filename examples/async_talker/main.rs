@@ -25,13 +25,16 @@ fn main() {
   let chatter_publisher = node
     .create_publisher::<String>(&chatter_topic, None)
     .unwrap();
-  let mut count = 0;
 
   let filler: String =
     "All work and no play makes ROS a dull boy. All play and no work makes RTPS a mere toy. "
       .repeat(2);
 
-  smol::block_on(async {
+  // Spinning is needed to know when subscribers are matched
+  ros2_client::spin(&mut node, |node| async move {
+    let mut count = 0;
+    println!("Waiting for a subscriber");
+    chatter_publisher.wait_for_subscribers(node, 1).await;
     loop {
       count += 1;
       let message = format!("count={} {}", count, filler);
@@ -39,5 +42,6 @@ fn main() {
       let _ = chatter_publisher.async_publish(message).await;
       Timer::after(std::time::Duration::from_millis(2)).await;
     }
-  });
+  })
+  .unwrap();
 }
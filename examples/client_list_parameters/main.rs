@@ -42,8 +42,7 @@ fn main() {
 
   println!(">>> request sending...");
   let request = ListParametersRequest {
-    DEPTH_RECURSIVE: 0,
-    depth: 0,
+    depth: ListParametersRequest::DEPTH_RECURSIVE,
     prefixes: vec![],
   };
 
@@ -131,7 +131,7 @@ fn main() {
                         i+=1;
                         fib.push( fib[i-2] + fib[i-1] );
                         fibonacci_action_server
-                          .publish_feedback(executing_goal, fib.clone())
+                          .publish_feedback(&executing_goal, fib.clone())
                           .await.unwrap();
                         info!("Publish feedback goal_id={:?}", executing_goal.goal_id());
                         if i == fib_order {
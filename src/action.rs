@@ -1,6 +1,9 @@
 use std::{
-  collections::{btree_map::Entry, BTreeMap},
+  collections::{btree_map::Entry, BTreeMap, BTreeSet},
+  fmt,
   marker::PhantomData,
+  sync::Mutex,
+  time::Instant,
 };
 
 use rustdds::{
@@ -13,6 +16,7 @@ use builtin_interfaces::Time;
 #[allow(unused_imports)]
 use log::{debug, error, info, warn};
 use futures::{
+  future::{Either, FutureExt},
   pin_mut,
   stream::{FusedStream, Stream, StreamExt},
   Future,
@@ -20,8 +24,11 @@ use futures::{
 
 use crate::{
   action_msgs, builtin_interfaces,
+  clock::Clock,
   message::Message,
   names::Name,
+  node::MatchedEndpoints,
+  runtime,
   service::{request_id::RmwRequestId, AService, CallServiceError, Client, Server},
   unique_identifier_msgs, Publisher, Subscription,
 };
@@ -166,6 +173,10 @@ where
   pub(crate) my_status_subscription: Subscription<action_msgs::GoalStatusArray>,
 
   pub(crate) my_action_name: Name,
+
+  // Goals rejected in response to async_send_goal, until observed by
+  // async_request_result or status_stream
+  pub(crate) rejected_goals: Mutex<BTreeSet<GoalId>>,
 }
 
 impl<A> ActionClient<A>
@@ -257,9 +268,58 @@ where
       .my_goal_client
       .async_call_service(SendGoalRequest { goal_id, goal })
       .await?;
+    if !send_goal_response.accepted {
+      self.rejected_goals.lock().unwrap().insert(goal_id);
+    }
     Ok((goal_id, send_goal_response))
   }
 
+  /// Was the goal rejected by the server, when it was sent with
+  /// [`Self::async_send_goal`].
+  ///
+  /// The server does not report rejected goals in its status updates, nor will
+  /// it answer a result request for them, because it does not keep track of
+  /// goals it did not accept. The only indication of rejection is
+  /// [`SendGoalResponse::accepted`], so the client remembers it, until either
+  /// [`Self::async_request_result`] or [`Self::status_stream`] has reported
+  /// the rejection.
+  pub fn is_goal_rejected(&self, goal_id: GoalId) -> bool {
+    self.rejected_goals.lock().unwrap().contains(&goal_id)
+  }
+
+  // Forget a rejected goal, once the rejection has been reported. Otherwise
+  // the set would grow for as long as the client exists.
+  fn take_rejected_goal(&self, goal_id: GoalId) -> bool {
+    self.rejected_goals.lock().unwrap().remove(&goal_id)
+  }
+
+  /// Send a goal, and if the server accepts it, wait for its result.
+  ///
+  /// Returns the GoalId, and either the end status and result of the goal, or
+  /// the rejecting response of the server. Feedback of the goal can be
+  /// observed with [`Self::feedback_stream`] using the GoalId.
+  pub async fn async_send_goal_and_await_result(
+    &self,
+    goal: A::GoalType,
+  ) -> Result<
+    (
+      GoalId,
+      Result<(GoalStatusEnum, A::ResultType), SendGoalResponse>,
+    ),
+    CallServiceError<()>,
+  >
+  where
+    <A as ActionTypes>::GoalType: 'static,
+    <A as ActionTypes>::ResultType: 'static,
+  {
+    let (goal_id, send_goal_response) = self.async_send_goal(goal).await?;
+    if !send_goal_response.accepted {
+      return Ok((goal_id, Err(send_goal_response)));
+    }
+    let result = self.async_request_result_from_server(goal_id).await?;
+    Ok((goal_id, Ok(result)))
+  }
+
   // From ROS2 docs:
   // https://docs.ros2.org/foxy/api/action_msgs/srv/CancelGoal.html
   //
@@ -358,9 +418,27 @@ where
   /// Result should be requested as soon as a goal is accepted.
   /// Result ia actually received only when Server informs that the goal has
   /// either Succeeded, or has been Canceled or Aborted.
+  ///
+  /// If the goal was rejected, this returns
+  /// [`RequestResultError::GoalRejected`] immediately, because the server
+  /// would never answer. See [`Self::is_goal_rejected`]. The rejection is
+  /// reported only once.
   pub async fn async_request_result(
     &self,
     goal_id: GoalId,
+  ) -> Result<(GoalStatusEnum, A::ResultType), RequestResultError>
+  where
+    <A as ActionTypes>::ResultType: 'static,
+  {
+    if self.take_rejected_goal(goal_id) {
+      return Err(RequestResultError::GoalRejected);
+    }
+    Ok(self.async_request_result_from_server(goal_id).await?)
+  }
+
+  async fn async_request_result_from_server(
+    &self,
+    goal_id: GoalId,
   ) -> Result<(GoalStatusEnum, A::ResultType), CallServiceError<()>>
   where
     <A as ActionTypes>::ResultType: 'static,
@@ -446,12 +524,22 @@ where
       .map(|result| result.map(|(gsa, _mi)| gsa))
   }
 
+  /// Async Stream of status updates of one goal.
+  ///
+  /// The stream of an accepted goal never ends, even after the goal has
+  /// ended, so an ended stream always means that the goal was rejected. If
+  /// the goal was rejected (see [`Self::is_goal_rejected`]), the stream ends
+  /// immediately without any items, as the server will never report status
+  /// for it. The rejection is reported only once, so a later stream for the
+  /// same goal does not end.
   pub fn status_stream(
     &self,
     goal_id: GoalId,
   ) -> impl Stream<Item = ReadResult<action_msgs::GoalStatus>> + FusedStream + '_ {
+    let rejected = self.take_rejected_goal(goal_id);
     self
       .all_statuses_stream()
+      .take_while(move |_| futures::future::ready(!rejected))
       .filter_map(move |result| async move {
         match result {
           Err(e) => Some(Err(e)),
@@ -506,6 +594,11 @@ where
   pub(crate) my_status_publisher: Publisher<action_msgs::GoalStatusArray>,
 
   pub(crate) my_action_name: Name,
+
+  pub(crate) my_clock: Clock,
+
+  // Remote writers matched to our readers, as in Node
+  pub(crate) my_matched_writers: MatchedEndpoints,
 }
 
 impl<A> ActionServer<A>
@@ -675,6 +768,31 @@ pub enum GoalEndStatus {
   Canceled,
 }
 
+/// Error from [`ActionClient::async_request_result`]
+#[derive(Debug)]
+pub enum RequestResultError {
+  /// The server rejected the goal, so it will never have a result.
+  GoalRejected,
+  CallServiceError(CallServiceError<()>),
+}
+
+impl From<CallServiceError<()>> for RequestResultError {
+  fn from(e: CallServiceError<()>) -> Self {
+    RequestResultError::CallServiceError(e)
+  }
+}
+
+impl fmt::Display for RequestResultError {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match self {
+      RequestResultError::GoalRejected => write!(f, "Goal was rejected by the server"),
+      RequestResultError::CallServiceError(e) => write!(f, "Result request failed: {e:?}"),
+    }
+  }
+}
+
+impl std::error::Error for RequestResultError {}
+
 #[derive(Debug)]
 pub enum GoalError<T> {
   NoSuchGoal,
@@ -702,6 +820,7 @@ where
   status: GoalStatusEnum,
   accepted_time: Option<builtin_interfaces::Time>,
   goal: A::GoalType,
+  client: GUID, // request writer of the goal client
 }
 
 pub struct AsyncActionServer<A>
@@ -713,9 +832,19 @@ where
 {
   actionserver: ActionServer<A>,
   goals: BTreeMap<GoalId, AsyncGoal<A>>,
-  result_requests: BTreeMap<GoalId, RmwRequestId>,
+  // Results of goals that have reached a terminal state. These are kept, so
+  // that result requests can be answered also after the goal has ended.
+  results: BTreeMap<GoalId, GetResultResponse<A::ResultType>>,
+  // When the results above, and their goals, are forgotten.
+  result_expiry_times: BTreeMap<GoalId, Instant>,
+  result_timeout: std::time::Duration,
+  // Result requests that are waiting for their goal to end.
+  result_requests: BTreeMap<GoalId, Vec<RmwRequestId>>,
 }
 
+/// Default of [`AsyncActionServer::result_timeout`], same as in rcl.
+pub const DEFAULT_RESULT_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(15 * 60);
+
 impl<A> AsyncActionServer<A>
 where
   A: ActionTypes,
@@ -727,32 +856,84 @@ where
     AsyncActionServer::<A> {
       actionserver,
       goals: BTreeMap::new(),
+      results: BTreeMap::new(),
+      result_expiry_times: BTreeMap::new(),
+      result_timeout: DEFAULT_RESULT_TIMEOUT,
       result_requests: BTreeMap::new(),
     }
   }
 
+  /// Set how long the result of an ended goal is kept available for result
+  /// requests. After that the goal is forgotten, and no longer reported in
+  /// goal statuses.
+  ///
+  /// Expired goals are removed while [`Self::receive_new_goal`] is waiting.
+  /// The default is [`DEFAULT_RESULT_TIMEOUT`].
+  pub fn result_timeout(mut self, result_timeout: std::time::Duration) -> Self {
+    self.result_timeout = result_timeout;
+    self
+  }
+
   pub fn get_new_goal(&self, handle: NewGoalHandle<A::GoalType>) -> Option<&A::GoalType> {
     self.goals.get(&handle.inner.goal_id).map(|ag| &ag.goal)
   }
 
   /// Receive a new goal from an action client.
   /// Server should immediately either accept or reject the goal.
+  ///
+  /// While waiting, this also answers result requests for goals that have
+  /// already ended.
   pub async fn receive_new_goal(&mut self) -> ReadResult<NewGoalHandle<A::GoalType>>
   where
     <A as ActionTypes>::GoalType: 'static,
+    <A as ActionTypes>::ResultType: 'static + Default,
   {
+    self.remove_lost_new_goals();
     let (req_id, goal_id) = loop {
-      let (req_id, goal_request) = self
-        .actionserver
-        .my_goal_server
-        .async_receive_request()
-        .await?;
+      self.remove_expired_results().await;
+      let received = {
+        let goal_request = self
+          .actionserver
+          .my_goal_server
+          .async_receive_request_with_info()
+          .fuse();
+        let result_request = self
+          .actionserver
+          .my_result_server
+          .async_receive_request()
+          .fuse();
+        let expiry = match self.result_expiry_times.values().min() {
+          Some(expiry_time) => {
+            runtime::sleep(expiry_time.saturating_duration_since(Instant::now())).left_future()
+          }
+          None => futures::future::pending::<()>().right_future(),
+        }
+        .fuse();
+        pin_mut!(goal_request, result_request, expiry);
+        futures::select! {
+          g = goal_request => Some(Either::Left(g?)),
+          r = result_request => Some(Either::Right(r?)),
+          _ = expiry => None,
+        }
+      };
+      let (req_id, goal_request, request_info) = match received {
+        None => continue, // some result has expired
+        Some(Either::Left(goal)) => goal,
+        Some(Either::Right((req_id, GetResultRequest { goal_id }))) => {
+          self
+            .handle_result_request(req_id, goal_id)
+            .await
+            .unwrap_or_else(|e| error!("Cannot respond to result request: {:?}", e));
+          continue;
+        }
+      };
       match self.goals.entry(goal_request.goal_id) {
         e @ Entry::Vacant(_) => {
           e.or_insert(AsyncGoal {
             status: GoalStatusEnum::Unknown,
             goal: goal_request.goal,
             accepted_time: None,
+            client: request_info.writer_guid(),
           });
           break (req_id, goal_request.goal_id);
         }
@@ -772,10 +953,71 @@ where
     Ok(NewGoalHandle { inner, req_id })
   }
 
+  // New goals, i.e. neither accepted nor rejected yet, whose client has
+  // disappeared, will never be of interest to anyone. Forget them.
+  //
+  // Clients are known to be lost only if the Node Spinner is running.
+  fn remove_lost_new_goals(&mut self)
+  where
+    A::ResultType: 'static + Default,
+  {
+    let lost: Vec<GoalId> = {
+      let matched_writers = self.actionserver.my_matched_writers.lock().unwrap();
+      let goal_clients =
+        match matched_writers.get(&self.actionserver.my_goal_server.request_reader_guid()) {
+          Some(goal_clients) => goal_clients,
+          None => return, // no discovery information
+        };
+      self
+        .goals
+        .iter()
+        .filter(|(_, goal)| {
+          goal.status == GoalStatusEnum::Unknown && !goal_clients.contains(&goal.client)
+        })
+        .map(|(goal_id, _)| *goal_id)
+        .collect()
+    };
+    for goal_id in lost {
+      info!("Client of new goal {goal_id:?} is lost. Removing goal.");
+      self.goals.remove(&goal_id);
+      self
+        .drop_result_requests(goal_id)
+        .unwrap_or_else(|e| error!("Cannot respond to result request: {:?}", e));
+    }
+  }
+
+  // Forget ended goals whose result timeout has passed.
+  async fn remove_expired_results(&mut self)
+  where
+    A::ResultType: 'static + Default,
+  {
+    let now = Instant::now();
+    let expired: Vec<GoalId> = self
+      .result_expiry_times
+      .iter()
+      .filter(|(_, expiry_time)| **expiry_time <= now)
+      .map(|(goal_id, _)| *goal_id)
+      .collect();
+    for goal_id in &expired {
+      debug!("Result of goal {goal_id:?} expired.");
+      self.result_expiry_times.remove(goal_id);
+      self.results.remove(goal_id);
+      self.goals.remove(goal_id);
+      self
+        .drop_result_requests(*goal_id)
+        .unwrap_or_else(|e| error!("Cannot respond to result request: {:?}", e));
+    }
+    if !expired.is_empty() {
+      self.publish_statuses().await;
+    }
+  }
+
   /// Convert a newly received goal into a accepted goal, i.e. accept it
   /// for execution later. Client will be notified of acceptance.
-  /// Note: Once the goal is accepted, the server must eventually call
-  /// `.send_result_response()` even if the goal is canceled or aborted.
+  /// Note: Once the goal is accepted, the server must eventually end it by
+  /// calling `.succeed_goal()`, `.abort_accepted_goal()`,
+  /// `.abort_executing_goal()`, or `.send_result_response()`, even if the goal
+  /// is canceled.
   pub async fn accept_goal(
     &mut self,
     handle: NewGoalHandle<A::GoalType>,
@@ -790,7 +1032,7 @@ where
           status: GoalStatusEnum::Unknown,
           ..
         } => {
-          let now = builtin_interfaces::Time::now();
+          let now = self.actionserver.my_clock.now();
           let mut_o = o.into_mut();
           mut_o.status = GoalStatusEnum::Accepted;
           mut_o.accepted_time = Some(now);
@@ -828,6 +1070,7 @@ where
   ) -> Result<(), GoalError<()>>
   where
     A::GoalType: 'static,
+    A::ResultType: 'static + Default,
   {
     match self.goals.entry(handle.inner.goal_id) {
       Entry::Vacant(_) => Err(GoalError::NoSuchGoal),
@@ -837,16 +1080,17 @@ where
             status: GoalStatusEnum::Unknown,
             ..
           } => {
+            // There is no Rejected state, and rejection is not reported in
+            // statuses, so the goal can be forgotten right away.
+            o.remove();
             self.actionserver.my_goal_server.send_response(
               handle.req_id,
               SendGoalResponse {
                 accepted: false,
-                stamp: builtin_interfaces::Time::now(),
+                stamp: self.actionserver.my_clock.now(),
               },
             )?;
-            //o.into_mut().0 = GoalStatusEnum::Rejected; -- there is no such state
-            //self.publish_statuses().await; -- this is not reported
-            Ok(())
+            self.drop_result_requests(handle.inner.goal_id)
           }
           AsyncGoal {
             status: wrong_status,
@@ -863,7 +1107,7 @@ where
     }
   }
 
-  /// Convert an accepted goal into a expecting goal, i.e. start the execution.
+  /// Convert an accepted goal into an executing goal, i.e. start the execution.
   /// Executing goal can publish feedback.
   pub async fn start_executing_goal(
     &mut self,
@@ -897,9 +1141,11 @@ where
   }
 
   /// Publish feedback on how the execution is proceeding.
+  ///
+  /// This can be called repeatedly while the goal is executing.
   pub async fn publish_feedback(
     &mut self,
-    handle: ExecutingGoalHandle<A::GoalType>,
+    handle: &ExecutingGoalHandle<A::GoalType>,
     feedback: A::FeedbackType,
   ) -> Result<(), GoalError<FeedbackMessage<A::FeedbackType>>> {
     match self.goals.entry(handle.inner.goal_id) {
@@ -930,9 +1176,8 @@ where
 
   /// Notify Client that a goal end state was reached and
   /// what was the result of the action.
-  /// This async will not resolve until the action client has requested for the
-  /// result, but the client should request the result as soon as server
-  /// accepts the goal.
+  ///
+  /// The result is delivered to the action client like in `.succeed_goal()`.
   // TODO: It is a bit silly that we have to supply a "result" even though
   // goal got canceled. But we have to send something in the ResultResponse.
   // And where does it say that result is not significant if cancelled or aborted?
@@ -943,7 +1188,7 @@ where
     result: A::ResultType,
   ) -> Result<(), GoalError<()>>
   where
-    A::ResultType: 'static,
+    A::ResultType: 'static + Default,
   {
     // We translate from interface type to internal type to ensure that
     // the end status is an end status and not e.g. "Accepted".
@@ -953,183 +1198,277 @@ where
       GoalEndStatus::Canceled => GoalStatusEnum::Canceled,
     };
 
-    // First, we must get a result request.
-    // It may already have been read or not.
-    // We will read these into a buffer, because there may be requests for
-    // other goals' results also.
-    let req_id = match self.result_requests.get(&handle.inner.goal_id) {
-      Some(req_id) => *req_id,
-      None => {
-        let res_reqs = self.actionserver.my_result_server.receive_request_stream();
-        pin_mut!(res_reqs);
-        loop {
-          // result request was not yet here. Keep receiving until we get it.
-          let (req_id, GetResultRequest { goal_id }) = res_reqs.select_next_some().await?;
-          if goal_id == handle.inner.goal_id {
-            break req_id;
-          } else {
-            self.result_requests.insert(goal_id, req_id);
-            debug!(
-              "Got result request for goal_id={:?} req_id={:?}",
-              goal_id, req_id
-            );
-            // and loop to wait for the next
-          }
-        }
-      }
-    };
+    // Accepted, executing, or canceling goal can be canceled or aborted
+    // TODO: Accepted goal cannot succeed, it must be executing before success.
+    self
+      .finish_goal(
+        handle.inner,
+        &[
+          GoalStatusEnum::Accepted,
+          GoalStatusEnum::Executing,
+          GoalStatusEnum::Canceling,
+        ],
+        result_status,
+        result,
+      )
+      .await
+  }
 
-    match self.goals.entry(handle.inner.goal_id) {
-      Entry::Vacant(_) => Err(GoalError::NoSuchGoal),
-      Entry::Occupied(o) => {
-        match o.get() {
-          // Accepted, executing, or canceling goal can be canceled or aborted
-          // TODO: Accepted goal cannot succeed, it must be executing before success.
-          AsyncGoal {
-            status: GoalStatusEnum::Accepted,
-            ..
-          }
-          | AsyncGoal {
-            status: GoalStatusEnum::Executing,
-            ..
-          }
-          | AsyncGoal {
-            status: GoalStatusEnum::Canceling,
-            ..
-          } => {
-            o.into_mut().status = result_status;
-            self.publish_statuses().await;
-            self.actionserver.send_result(
-              req_id,
-              GetResultResponse {
-                status: result_status,
-                result,
-              },
-            )?;
-            debug!(
-              "Send result for goal_id={:?}  req_id={:?}",
-              handle.inner.goal_id, req_id
-            );
-            Ok(())
-          }
-          AsyncGoal {
-            status: wrong_status,
-            ..
-          } => {
-            error!(
-              "Tried to finish goal {:?} but status was {:?}.",
-              handle.inner.goal_id, wrong_status
-            );
-            Err(GoalError::WrongGoalState)
-          }
-        }
-      }
-    }
+  /// Finish goal execution successfully.
+  ///
+  /// The result is sent to the action client as a response to its result
+  /// request, either now, if the client has already asked for it, or
+  /// later, when the request arrives.
+  pub async fn succeed_goal(
+    &mut self,
+    handle: ExecutingGoalHandle<A::GoalType>,
+    result: A::ResultType,
+  ) -> Result<(), GoalError<()>>
+  where
+    A::ResultType: 'static + Default,
+  {
+    self
+      .finish_goal(
+        handle.inner,
+        &[GoalStatusEnum::Executing],
+        GoalStatusEnum::Succeeded,
+        result,
+      )
+      .await
   }
 
   /// Abort goal execution, because action server has determined it
   /// cannot continue execution.
+  ///
+  /// The result is delivered to the action client like in `.succeed_goal()`.
   pub async fn abort_executing_goal(
     &mut self,
     handle: ExecutingGoalHandle<A::GoalType>,
-  ) -> Result<(), GoalError<()>> {
-    self.abort_goal(handle.inner).await
+    result: A::ResultType,
+  ) -> Result<(), GoalError<()>>
+  where
+    A::ResultType: 'static + Default,
+  {
+    self.abort_goal(handle.inner, result).await
   }
+
+  /// Abort a goal before it started executing.
+  ///
+  /// The result is delivered to the action client like in `.succeed_goal()`.
   pub async fn abort_accepted_goal(
     &mut self,
     handle: AcceptedGoalHandle<A::GoalType>,
-  ) -> Result<(), GoalError<()>> {
-    self.abort_goal(handle.inner).await
+    result: A::ResultType,
+  ) -> Result<(), GoalError<()>>
+  where
+    A::ResultType: 'static + Default,
+  {
+    self.abort_goal(handle.inner, result).await
   }
 
   async fn abort_goal(
     &mut self,
     handle: InnerGoalHandle<A::GoalType>,
-  ) -> Result<(), GoalError<()>> {
-    match self.goals.entry(handle.goal_id) {
-      Entry::Vacant(_) => Err(GoalError::NoSuchGoal),
-      Entry::Occupied(o) => match o.get() {
-        AsyncGoal {
-          status: GoalStatusEnum::Accepted,
-          ..
-        }
-        | AsyncGoal {
-          status: GoalStatusEnum::Executing,
-          ..
-        } => {
-          o.into_mut().status = GoalStatusEnum::Aborted;
-          self.publish_statuses().await;
-          Ok(())
-        }
-        AsyncGoal {
-          status: wrong_status,
-          ..
-        } => {
-          error!(
-            "Tried to abort goal {:?} but status was {:?}, expected Accepted or Executing. ",
-            handle.goal_id, wrong_status
-          );
-          Err(GoalError::WrongGoalState)
-        }
+    result: A::ResultType,
+  ) -> Result<(), GoalError<()>>
+  where
+    A::ResultType: 'static + Default,
+  {
+    self
+      .finish_goal(
+        handle,
+        &[GoalStatusEnum::Accepted, GoalStatusEnum::Executing],
+        GoalStatusEnum::Aborted,
+        result,
+      )
+      .await
+  }
+
+  // Move goal to a terminal state and store the result for result requests.
+  // Result requests that are already waiting are answered.
+  async fn finish_goal(
+    &mut self,
+    handle: InnerGoalHandle<A::GoalType>,
+    allowed_states: &[GoalStatusEnum],
+    end_status: GoalStatusEnum,
+    result: A::ResultType,
+  ) -> Result<(), GoalError<()>>
+  where
+    A::ResultType: 'static + Default,
+  {
+    match self.goals.get_mut(&handle.goal_id) {
+      None => return Err(GoalError::NoSuchGoal),
+      Some(goal) if allowed_states.contains(&goal.status) => goal.status = end_status,
+      Some(goal) => {
+        error!(
+          "Tried to end goal {:?} as {:?} but status was {:?}, expected one of {:?}.",
+          handle.goal_id, end_status, goal.status, allowed_states
+        );
+        return Err(GoalError::WrongGoalState);
+      }
+    }
+    self.publish_statuses().await;
+    self.results.insert(
+      handle.goal_id,
+      GetResultResponse {
+        status: end_status,
+        result,
       },
+    );
+    self
+      .result_expiry_times
+      .insert(handle.goal_id, Instant::now() + self.result_timeout);
+
+    // Pick up result requests that have arrived in the meantime.
+    while let Some((req_id, GetResultRequest { goal_id })) =
+      self.actionserver.receive_result_request()?
+    {
+      self.handle_result_request(req_id, goal_id).await?;
     }
+    // Also answer requests for this goal that were buffered earlier.
+    self.respond_to_result_requests(handle.goal_id).await
+  }
+
+  // Respond to a result request now, if the goal has ended, or buffer it to
+  // wait for the end. Requests for unknown goals are answered immediately
+  // with status Unknown and a default result, like in rcl.
+  async fn handle_result_request(
+    &mut self,
+    req_id: RmwRequestId,
+    goal_id: GoalId,
+  ) -> Result<(), GoalError<()>>
+  where
+    A::ResultType: 'static + Default,
+  {
+    debug!(
+      "Got result request for goal_id={:?} req_id={:?}",
+      goal_id, req_id
+    );
+    if !self.goals.contains_key(&goal_id) {
+      self.send_unknown_goal_result(req_id)?;
+      return Ok(());
+    }
+    self
+      .result_requests
+      .entry(goal_id)
+      .or_insert_with(Vec::new)
+      .push(req_id);
+    self.respond_to_result_requests(goal_id).await
+  }
+
+  // A goal that is not known will never have a result. Tell the client that
+  // it is unknown, with a default result, as rcl does.
+  fn send_unknown_goal_result(&self, req_id: RmwRequestId) -> Result<(), GoalError<()>>
+  where
+    A::ResultType: 'static + Default,
+  {
+    self.actionserver.send_result(
+      req_id,
+      GetResultResponse {
+        status: GoalStatusEnum::Unknown,
+        result: A::ResultType::default(),
+      },
+    )?;
+    Ok(())
+  }
+
+  // Result requests buffered for a goal that is forgotten before it has a
+  // result, e.g. because it was rejected or its client was lost, are answered
+  // as for an unknown goal, so that they are not kept forever.
+  fn drop_result_requests(&mut self, goal_id: GoalId) -> Result<(), GoalError<()>>
+  where
+    A::ResultType: 'static + Default,
+  {
+    for req_id in self.result_requests.remove(&goal_id).unwrap_or_default() {
+      debug!("Goal {goal_id:?} was forgotten. Answering result request {req_id:?} as unknown.");
+      self.send_unknown_goal_result(req_id)?;
+    }
+    Ok(())
+  }
+
+  // The goal is still reported in statuses, until its result expires.
+  async fn respond_to_result_requests(&mut self, goal_id: GoalId) -> Result<(), GoalError<()>>
+  where
+    A::ResultType: 'static + Default,
+  {
+    let response = match self.results.get(&goal_id) {
+      Some(response) => response,
+      None => return Ok(()), // goal has not ended yet
+    };
+    let req_ids = self.result_requests.remove(&goal_id).unwrap_or_default();
+    for req_id in &req_ids {
+      self.actionserver.send_result(*req_id, response.clone())?;
+      debug!("Send result for goal_id={:?}  req_id={:?}", goal_id, req_id);
+    }
+    Ok(())
   }
 
   /// Receive a set of cancel requests from the action client.
   /// The server should now respond either by accepting (some of) the
   /// cancel requests or rejecting all of them. The GoalIds that are requested
   /// to be cancelled can be currently at either accepted or executing state.
+  ///
+  /// Requests to cancel a goal that is unknown or has already ended are
+  /// answered automatically, and are not returned.
   pub async fn receive_cancel_request(&self) -> ReadResult<CancelHandle> {
-    let (req_id, CancelGoalRequest { goal_info }) = self
-      .actionserver
-      .my_cancel_server
-      .async_receive_request()
-      .await?;
-
-    #[allow(clippy::type_complexity)] // How would you refactor this type?
-    let goal_filter: Box<dyn FnMut(&(&GoalId, &AsyncGoal<A>)) -> bool> = match goal_info {
-      GoalInfo {
-        goal_id: GoalId::ZERO,
-        stamp: builtin_interfaces::Time::ZERO,
-      } => Box::new(|(_, _)| true), // cancel all goals
-
-      GoalInfo {
-        goal_id: GoalId::ZERO,
-        stamp,
-      } => Box::new(move |(_, ag)| ag.accepted_time.map(|at| at < stamp).unwrap_or(false)),
-
-      GoalInfo {
-        goal_id,
-        stamp: builtin_interfaces::Time::ZERO,
-      } => Box::new(move |(g_id, _)| goal_id == **g_id),
+    loop {
+      let (req_id, CancelGoalRequest { goal_info }) = self
+        .actionserver
+        .my_cancel_server
+        .async_receive_request()
+        .await?;
 
-      GoalInfo { goal_id, stamp } => Box::new(move |(g_id, ag)| {
-        goal_id == **g_id || ag.accepted_time.map(move |at| at < stamp).unwrap_or(false)
-      }),
-    };
+      // Check if the specifically requested goal is something we cannot
+      // cancel. In those cases outright send a negative response.
+      if goal_info.goal_id != GoalId::ZERO && goal_info.stamp == builtin_interfaces::Time::ZERO {
+        let refusal = match self.goals.get(&goal_info.goal_id) {
+          None => Some(action_msgs::CancelGoalResponseEnum::UnknownGoal),
+          Some(AsyncGoal {
+            status: GoalStatusEnum::Succeeded | GoalStatusEnum::Aborted | GoalStatusEnum::Canceled,
+            ..
+          }) => Some(action_msgs::CancelGoalResponseEnum::GoalTerminated),
+          Some(_) => None,
+        };
+        if let Some(return_code) = refusal {
+          debug!(
+            "Refusing cancel request for goal {:?}: {:?}",
+            goal_info.goal_id, return_code
+          );
+          self
+            .actionserver
+            .my_cancel_server
+            .async_send_response(
+              req_id,
+              action_msgs::CancelGoalResponse {
+                return_code,
+                goals_canceling: vec![],
+              },
+            )
+            .await
+            .unwrap_or_else(|e| error!("Cannot send cancel response: {:?}", e));
+          continue;
+        }
+      }
 
-    // TODO:
-    // Should check if the specified GoalId was unknown to us
-    // or already terminated.
-    // In those case outright send a negative response and not return to the
-    // application.
-    let cancel_handle = CancelHandle {
-      req_id,
-      goals: self
-        .goals
-        .iter()
-        // only consider goals with status Executing or Accepted for Cancel
-        .filter(|(_, async_goal)| {
-          async_goal.status == GoalStatusEnum::Executing
-            || async_goal.status == GoalStatusEnum::Accepted
-        })
-        // and then filter those that were specified by the cancel request
-        .filter(goal_filter)
-        .map(|p| *p.0)
-        .collect(),
-    };
+      let cancel_handle = CancelHandle {
+        req_id,
+        goals: self
+          .goals
+          .iter()
+          .filter(|(goal_id, async_goal)| {
+            is_cancel_candidate(
+              &goal_info,
+              **goal_id,
+              async_goal.status,
+              async_goal.accepted_time,
+            )
+          })
+          .map(|p| *p.0)
+          .collect(),
+      };
 
-    Ok(cancel_handle)
+      return Ok(cancel_handle);
+    }
   }
 
   /// Respond to action client's cancel requests.
@@ -1178,7 +1517,11 @@ where
 
   // This function is private, because all status publishing happens automatically
   // via goal status changes.
+  //
+  // Goals are reported with their accept time. Goals that have not been
+  // accepted (yet) are stamped with the current time.
   async fn publish_statuses(&self) {
+    let now = self.actionserver.my_clock.now();
     let goal_status_array = action_msgs::GoalStatusArray {
       status_list: self
         .goals
@@ -1195,7 +1538,7 @@ where
             status: *status,
             goal_info: GoalInfo {
               goal_id: *goal_id,
-              stamp: accepted_time.unwrap_or(builtin_interfaces::Time::ZERO),
+              stamp: accepted_time.unwrap_or(now),
             },
           },
         )
@@ -1214,3 +1557,334 @@ where
       .unwrap_or_else(|e| error!("AsyncActionServer::publish_statuses: {:?}", e));
   }
 }
+
+// Decide if a goal should be canceled according to a cancel request.
+// Only goals with status Executing or Accepted are considered.
+// Cancel policy from ROS 2 action_msgs/srv/CancelGoal:
+// - goal_id zero, stamp zero: cancel all goals
+// - goal_id zero, stamp nonzero: cancel all goals accepted at or before stamp
+// - goal_id nonzero, stamp zero: cancel the goal with that id
+// - goal_id nonzero, stamp nonzero: cancel the goal with that id and all goals
+//   accepted at or before stamp
+fn is_cancel_candidate(
+  request: &GoalInfo,
+  goal_id: GoalId,
+  status: GoalStatusEnum,
+  accepted_time: Option<builtin_interfaces::Time>,
+) -> bool {
+  let cancelable = status == GoalStatusEnum::Executing || status == GoalStatusEnum::Accepted;
+  let accepted_before_stamp = || accepted_time.map_or(false, |at| at <= request.stamp);
+  cancelable
+    && match request {
+      GoalInfo {
+        goal_id: GoalId::ZERO,
+        stamp: builtin_interfaces::Time::ZERO,
+      } => true,
+      GoalInfo {
+        goal_id: GoalId::ZERO,
+        ..
+      } => accepted_before_stamp(),
+      GoalInfo {
+        goal_id: requested_id,
+        stamp: builtin_interfaces::Time::ZERO,
+      } => *requested_id == goal_id,
+      GoalInfo {
+        goal_id: requested_id,
+        ..
+      } => *requested_id == goal_id || accepted_before_stamp(),
+    }
+}
+
+#[test]
+fn cancel_policy() {
+  use builtin_interfaces::Time;
+
+  let goal = GoalId::new_random();
+  let other_goal = GoalId::new_random();
+  let accepted = Some(Time {
+    sec: 100,
+    nanosec: 0,
+  });
+  let later = Time {
+    sec: 200,
+    nanosec: 0,
+  };
+  let earlier = Time {
+    sec: 50,
+    nanosec: 0,
+  };
+  let request = |goal_id, stamp| GoalInfo { goal_id, stamp };
+  let cancels = |req: &GoalInfo, id, status| is_cancel_candidate(req, id, status, accepted);
+  use GoalStatusEnum::{Accepted, Canceling, Executing, Succeeded};
+
+  // cancel all
+  let all = request(GoalId::ZERO, Time::ZERO);
+  assert!(cancels(&all, goal, Accepted));
+  assert!(cancels(&all, goal, Executing));
+  assert!(!cancels(&all, goal, Canceling));
+  assert!(!cancels(&all, goal, Succeeded));
+
+  // cancel by time, inclusive
+  let by_time = |stamp| request(GoalId::ZERO, stamp);
+  assert!(cancels(&by_time(later), goal, Executing));
+  assert!(cancels(&by_time(accepted.unwrap()), goal, Executing));
+  assert!(!cancels(&by_time(earlier), goal, Executing));
+
+  // cancel by id
+  let by_id = request(goal, Time::ZERO);
+  assert!(cancels(&by_id, goal, Executing));
+  assert!(!cancels(&by_id, other_goal, Executing));
+
+  // cancel by id or time
+  let by_both = request(goal, earlier);
+  assert!(cancels(&by_both, goal, Executing));
+  assert!(!cancels(&by_both, other_goal, Executing));
+  let by_both = request(goal, later);
+  assert!(cancels(&by_both, other_goal, Executing));
+}
+
+#[test]
+#[ignore]
+fn rejected_goals_are_forgotten() {
+  use crate::test_util::{count_action, test_node};
+
+  let mut node = test_node("/reject_test", "node");
+  let spinner = node.spinner().unwrap();
+  std::thread::spawn(move || smol::block_on(spinner.spin()));
+  let (mut server, client) = count_action(&mut node);
+
+  let serve = async {
+    for _ in 0..50 {
+      let handle = server.receive_new_goal().await.unwrap();
+      server.reject_goal(handle).await.unwrap();
+      assert!(server.goals.is_empty());
+    }
+  };
+  let send = async {
+    for i in 0..50 {
+      let (_goal_id, response) = client.async_send_goal(i).await.unwrap();
+      assert!(!response.accepted);
+    }
+  };
+  smol::block_on(futures::future::join(serve, send));
+  assert!(server.goals.is_empty());
+}
+
+#[test]
+#[ignore]
+fn results_expire_after_timeout() {
+  use crate::test_util::{count_action, test_node};
+
+  let mut node = test_node("/expiry_test", "node");
+  let (server, client) = count_action(&mut node);
+  let mut server = server.result_timeout(std::time::Duration::from_secs(1));
+
+  let serve = async {
+    let handle = server.receive_new_goal().await.unwrap();
+    let handle = server.accept_goal(handle).await.unwrap();
+    let handle = server.start_executing_goal(handle).await.unwrap();
+    server.succeed_goal(handle, 42).await.unwrap();
+    // Answer the result request, and expire the result, while waiting
+    let more_goals = server.receive_new_goal().fuse();
+    let stop = runtime::sleep(std::time::Duration::from_secs(3)).fuse();
+    pin_mut!(more_goals, stop);
+    futures::select! {
+      _ = more_goals => panic!("No more goals were sent"),
+      _ = stop => {}
+    }
+  };
+  let send = async {
+    let (goal_id, response) = client.async_send_goal(7).await.unwrap();
+    assert!(response.accepted);
+    let (status, result) = client.async_request_result(goal_id).await.unwrap();
+    assert_eq!((status, result), (GoalStatusEnum::Succeeded, 42));
+    goal_id
+  };
+  let ((), goal_id) = smol::block_on(futures::future::join(serve, send));
+
+  assert!(server.goals.is_empty());
+  assert!(server.results.is_empty());
+  std::thread::sleep(std::time::Duration::from_millis(500));
+  let mut last_status = None;
+  while let Some(status) = client.receive_status().unwrap() {
+    last_status = Some(status);
+  }
+  assert!(!last_status
+    .unwrap()
+    .status_list
+    .iter()
+    .any(|gs| gs.goal_info.goal_id == goal_id));
+}
+
+#[test]
+#[ignore]
+fn send_goal_and_await_result_handles_rejection() {
+  use crate::test_util::{count_action, test_node};
+
+  let mut node = test_node("/await_result_test", "node");
+  let (mut server, client) = count_action(&mut node);
+
+  // Accepts positive goals, and counts them up by one. Rejects the rest.
+  let serve = async {
+    for _ in 0..2 {
+      let handle = server.receive_new_goal().await.unwrap();
+      let goal = *server.get_new_goal(handle).unwrap();
+      if goal > 0 {
+        let handle = server.accept_goal(handle).await.unwrap();
+        let handle = server.start_executing_goal(handle).await.unwrap();
+        server.succeed_goal(handle, goal + 1).await.unwrap();
+      } else {
+        server.reject_goal(handle).await.unwrap();
+      }
+    }
+  };
+  let send = async {
+    let (_, accepted) = client.async_send_goal_and_await_result(1).await.unwrap();
+    assert_eq!(accepted.unwrap(), (GoalStatusEnum::Succeeded, 2));
+    let (_, rejected) = client.async_send_goal_and_await_result(-1).await.unwrap();
+    assert!(!rejected.unwrap_err().accepted);
+  };
+  smol::block_on(futures::future::join(serve, send));
+}
+
+#[test]
+#[ignore]
+fn rejected_goal_result_resolves_promptly() {
+  use crate::test_util::{count_action, test_node};
+
+  let mut node = test_node("/rejected_goal_test", "node");
+  let (mut server, client) = count_action(&mut node);
+
+  let serve = async {
+    for _ in 0..2 {
+      let handle = server.receive_new_goal().await.unwrap();
+      server.reject_goal(handle).await.unwrap();
+    }
+  };
+  let send = async {
+    let (goal_id, response) = client.async_send_goal(1).await.unwrap();
+    assert!(!response.accepted);
+    assert!(client.is_goal_rejected(goal_id));
+
+    let start = Instant::now();
+    let result = client.async_request_result(goal_id).await;
+    assert!(matches!(result, Err(RequestResultError::GoalRejected)));
+    assert!(start.elapsed() < std::time::Duration::from_secs(1));
+    // Reported once, then forgotten
+    assert!(!client.is_goal_rejected(goal_id));
+
+    let (goal_id, response) = client.async_send_goal(2).await.unwrap();
+    assert!(!response.accepted);
+    let start = Instant::now();
+    let statuses: Vec<_> = client.status_stream(goal_id).collect().await;
+    assert!(statuses.is_empty());
+    assert!(start.elapsed() < std::time::Duration::from_secs(1));
+    assert!(!client.is_goal_rejected(goal_id));
+  };
+  smol::block_on(futures::future::join(serve, send));
+}
+
+#[test]
+#[ignore]
+fn result_requested_before_goal_end_is_answered() {
+  use crate::test_util::{count_action, test_node};
+
+  let mut node = test_node("/early_result_test", "node");
+  let (mut server, client) = count_action(&mut node);
+
+  let serve = async {
+    let handle = server.receive_new_goal().await.unwrap();
+    let handle = server.accept_goal(handle).await.unwrap();
+    let handle = server.start_executing_goal(handle).await.unwrap();
+    // Let the result request arrive before the goal ends.
+    runtime::sleep(std::time::Duration::from_secs(1)).await;
+    server.succeed_goal(handle, 8).await.unwrap();
+    // Answer result requests, while waiting
+    let more_goals = server.receive_new_goal().fuse();
+    let stop = runtime::sleep(std::time::Duration::from_secs(3)).fuse();
+    pin_mut!(more_goals, stop);
+    futures::select! {
+      _ = more_goals => panic!("No more goals were sent"),
+      _ = stop => {}
+    }
+  };
+  let request = async {
+    let (goal_id, response) = client.async_send_goal(7).await.unwrap();
+    assert!(response.accepted);
+    let result = client.async_request_result(goal_id).await.unwrap();
+    assert_eq!(result, (GoalStatusEnum::Succeeded, 8));
+    // Unknown goals get an answer right away
+    let unknown_goal = unique_identifier_msgs::UUID::new_random();
+    let result = client.async_request_result(unknown_goal).await.unwrap();
+    assert_eq!(result, (GoalStatusEnum::Unknown, 0));
+  };
+  smol::block_on(futures::future::join(serve, request));
+}
+
+#[test]
+#[ignore]
+fn feedback_stream_collects_feedback_of_goal() {
+  use crate::test_util::{count_action, test_node};
+
+  let mut node = test_node("/feedback_test", "node");
+  let (mut server, client) = count_action(&mut node);
+
+  // Counts up to the goal, publishing each number as feedback.
+  let serve = async {
+    let handle = server.receive_new_goal().await.unwrap();
+    let goal = *server.get_new_goal(handle).unwrap();
+    let handle = server.accept_goal(handle).await.unwrap();
+    let handle = server.start_executing_goal(handle).await.unwrap();
+    for i in 1..=goal {
+      server.publish_feedback(&handle, i).await.unwrap();
+    }
+    server.succeed_goal(handle, goal).await.unwrap();
+  };
+  let send = async {
+    let (goal_id, response) = client.async_send_goal(3).await.unwrap();
+    assert!(response.accepted);
+    client
+      .feedback_stream(goal_id)
+      .take(3)
+      .map(Result::unwrap)
+      .collect::<Vec<_>>()
+      .await
+  };
+  let ((), feedback) = smol::block_on(futures::future::join(serve, send));
+  assert_eq!(feedback, vec![1, 2, 3]);
+}
+
+#[test]
+#[ignore]
+fn client_sees_goal_start_executing() {
+  use crate::test_util::{count_action, test_node};
+
+  let mut node = test_node("/executing_test", "node");
+  let (mut server, client) = count_action(&mut node);
+  let (seen_sender, seen_receiver) = futures::channel::oneshot::channel();
+
+  let serve = async {
+    let handle = server.receive_new_goal().await.unwrap();
+    let handle = server.accept_goal(handle).await.unwrap();
+    let handle = server.start_executing_goal(handle).await.unwrap();
+    // Keep executing until the client has seen it
+    seen_receiver.await.unwrap();
+    server.succeed_goal(handle, 1).await.unwrap();
+  };
+  let send = async {
+    let (goal_id, response) = client.async_send_goal(1).await.unwrap();
+    assert!(response.accepted);
+    let statuses = client.status_stream(goal_id);
+    pin_mut!(statuses);
+    loop {
+      let status = statuses.next().await.unwrap().unwrap();
+      assert_eq!(status.goal_info.goal_id, goal_id);
+      if status.status == GoalStatusEnum::Executing {
+        break;
+      }
+      assert_eq!(status.status, GoalStatusEnum::Accepted);
+    }
+    seen_sender.send(()).unwrap();
+  };
+  smol::block_on(futures::future::join(serve, send));
+}
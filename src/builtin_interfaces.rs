@@ -44,6 +44,14 @@ impl Time {
   }
 }
 
+impl From<Time> for rustdds::Timestamp {
+  fn from(t: Time) -> rustdds::Timestamp {
+    rustdds::Timestamp::ZERO
+      + rustdds::Duration::from_secs(t.sec.max(0))
+      + rustdds::Duration::from_frac_seconds(f64::from(t.nanosec) / 1e9)
+  }
+}
+
 // TODO: Implement constructors and conversions to/from usual Rust time formats
 // Note that this type does not specify a zero point in time.
 
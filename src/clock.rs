@@ -0,0 +1,81 @@
+//! Time source of a [`Node`](crate::Node)
+
+use std::sync::{Arc, Mutex};
+
+#[allow(unused_imports)]
+use log::{debug, error, info, trace, warn};
+
+use crate::{builtin_interfaces::Time, pubsub::Subscription, rosgraph_msgs};
+
+/// Gives the current time, either from the system (wall) clock, or
+/// simulation time from the `/clock` Topic.
+///
+/// See [`Node::use_sim_time`](crate::Node::use_sim_time). Clones of a Clock
+/// share the time source, so switching to simulation time affects also clones
+/// obtained earlier.
+#[derive(Clone)]
+pub struct Clock {
+  sim_time: Arc<Mutex<Option<SimTime>>>, // None means system time
+}
+
+struct SimTime {
+  clock_subscription: Subscription<rosgraph_msgs::Clock>,
+  latest: Option<Time>,
+  warned_no_clock: bool,
+}
+
+impl Clock {
+  pub(crate) fn new() -> Clock {
+    Clock {
+      sim_time: Arc::new(Mutex::new(None)),
+    }
+  }
+
+  // Some(subscription) switches to simulation time, None to system time.
+  pub(crate) fn set_sim_time_source(
+    &self,
+    clock_subscription: Option<Subscription<rosgraph_msgs::Clock>>,
+  ) {
+    *self.sim_time.lock().unwrap() = clock_subscription.map(|clock_subscription| SimTime {
+      clock_subscription,
+      latest: None,
+      warned_no_clock: false,
+    });
+  }
+
+  /// Is simulation time in use?
+  pub fn is_sim_time(&self) -> bool {
+    self.sim_time.lock().unwrap().is_some()
+  }
+
+  /// Get the current time.
+  ///
+  /// With simulation time, this is the latest time received from `/clock`, or
+  /// zero if nothing has been received yet.
+  pub fn now(&self) -> Time {
+    let mut sim_time = self.sim_time.lock().unwrap();
+    match sim_time.as_mut() {
+      None => Time::now(),
+      Some(sim) => {
+        // Only the latest clock message matters.
+        loop {
+          match sim.clock_subscription.take() {
+            Ok(Some((clock_msg, _msg_info))) => sim.latest = Some(clock_msg.clock),
+            Ok(None) => break,
+            Err(e) => {
+              warn!("Reading /clock failed: {e:?}");
+              break;
+            }
+          }
+        }
+        sim.latest.unwrap_or_else(|| {
+          if !sim.warned_no_clock {
+            warn!("Simulation time is enabled, but nothing received from /clock yet.");
+            sim.warned_no_clock = true;
+          }
+          Time::ZERO
+        })
+      }
+    }
+  }
+}
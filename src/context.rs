@@ -1,5 +1,5 @@
 use std::{
-  collections::HashMap,
+  collections::{BTreeMap, HashMap},
   sync::{Arc, Mutex},
 };
 //use futures::{pin_mut, StreamExt};
@@ -21,9 +21,10 @@ use crate::{
   builtin_topics,
   entities_info::{NodeEntitiesInfo, ParticipantEntitiesInfo},
   gid::Gid,
+  graph::{EndpointKind, LocalEndpointRegistration, LocalEndpoints},
   names::NodeName,
   node::{Node, NodeOptions},
-  pubsub::{Publisher, Subscription},
+  pubsub::{KeyedPublisher, KeyedSubscription, Publisher, Subscription},
 };
 
 lazy_static! {
@@ -122,6 +123,7 @@ impl Default for ContextOptions {
 #[derive(Clone)]
 pub struct Context {
   inner: Arc<Mutex<ContextInner>>,
+  local_endpoints: LocalEndpoints,
 }
 
 impl Context {
@@ -155,6 +157,7 @@ impl Context {
     let i = ContextInner::from_domain_participant(domain_participant)?;
     Ok(Context {
       inner: Arc::new(Mutex::new(i)),
+      local_endpoints: Arc::new(Mutex::new(BTreeMap::new())),
     })
   }
 
@@ -222,8 +225,13 @@ impl Context {
     let datawriter = self
       .get_ros_default_publisher()
       .create_datawriter_no_key(topic, qos)?;
-
-    Ok(Publisher::new(datawriter))
+    let mut publisher = Publisher::new(datawriter);
+    publisher.set_registration(self.register_local_endpoint(
+      EndpointKind::Publisher,
+      publisher.guid(),
+      topic,
+    ));
+    Ok(publisher)
   }
 
   pub(crate) fn create_subscription<M>(
@@ -237,7 +245,78 @@ impl Context {
     let datareader = self
       .get_ros_default_subscriber()
       .create_simple_datareader_no_key(topic, qos)?;
-    Ok(Subscription::new(datareader))
+    let mut subscription = Subscription::new(datareader);
+    subscription.set_registration(self.register_local_endpoint(
+      EndpointKind::Subscription,
+      subscription.guid(),
+      topic,
+    ));
+    Ok(subscription)
+  }
+
+  pub(crate) fn create_keyed_publisher<M>(
+    &self,
+    topic: &Topic,
+    qos: Option<QosPolicies>,
+  ) -> dds::CreateResult<KeyedPublisher<M>>
+  where
+    M: Keyed + Serialize,
+    M::K: Key,
+  {
+    let datawriter = self
+      .get_ros_default_publisher()
+      .create_datawriter_cdr(topic, qos)?;
+    let mut publisher = KeyedPublisher::new(datawriter);
+    publisher.set_registration(self.register_local_endpoint(
+      EndpointKind::Publisher,
+      publisher.guid(),
+      topic,
+    ));
+    Ok(publisher)
+  }
+
+  pub(crate) fn create_keyed_subscription<M>(
+    &self,
+    topic: &Topic,
+    qos: Option<QosPolicies>,
+  ) -> dds::CreateResult<KeyedSubscription<M>>
+  where
+    M: 'static + Keyed + DeserializeOwned,
+    M::K: Key,
+  {
+    let datareader = self
+      .get_ros_default_subscriber()
+      .create_datareader_cdr(topic, qos)?;
+    let mut subscription = KeyedSubscription::new(datareader);
+    subscription.set_registration(self.register_local_endpoint(
+      EndpointKind::Subscription,
+      subscription.guid(),
+      topic,
+    ));
+    Ok(subscription)
+  }
+
+  // The endpoint is counted by count_local_endpoints for as long as the
+  // returned registration exists.
+  fn register_local_endpoint(
+    &self,
+    kind: EndpointKind,
+    guid: GUID,
+    topic: &Topic,
+  ) -> LocalEndpointRegistration {
+    LocalEndpointRegistration::new(&self.local_endpoints, kind, guid, topic.name())
+  }
+
+  // Number of live Publishers or Subscriptions on a DDS Topic, created by any
+  // Node of this Context.
+  pub(crate) fn count_local_endpoints(&self, kind: EndpointKind, dds_topic_name: &str) -> usize {
+    self
+      .local_endpoints
+      .lock()
+      .unwrap()
+      .values()
+      .filter(|(k, name)| *k == kind && name == dds_topic_name)
+      .count()
   }
 
   pub(crate) fn create_datawriter<M, SA>(
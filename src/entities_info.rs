@@ -78,6 +78,10 @@ impl NodeEntitiesInfo {
     self.name.base_name()
   }
 
+  pub fn node_name(&self) -> NodeName {
+    self.name.clone()
+  }
+
   /// Full name of the node namespace + name eg. /some_node
   pub fn fully_qualified_name(&self) -> String {
     self.name.fully_qualified_name()
@@ -94,6 +98,14 @@ impl NodeEntitiesInfo {
       self.reader_gid_seq.push(gid);
     }
   }
+
+  pub(crate) fn has_writer(&self, gid: Gid) -> bool {
+    self.writer_gid_seq.contains(&gid)
+  }
+
+  pub(crate) fn has_reader(&self, gid: Gid) -> bool {
+    self.reader_gid_seq.contains(&gid)
+  }
 }
 
 impl TryFrom<repr::NodeEntitiesInfo> for NodeEntitiesInfo {
@@ -0,0 +1,467 @@
+//! Higher-level view of DDS and ROS 2 Discovery events.
+//!
+//! [`NodeEvent`](crate::NodeEvent)s are raw discovery data. Here they are
+//! turned into changes of the ROS 2 graph, i.e. Nodes, Publishers and
+//! Subscriptions appearing and disappearing.
+
+use std::{
+  collections::{BTreeMap, BTreeSet},
+  sync::{Arc, Mutex, Weak},
+};
+
+use rustdds::GUID;
+
+use crate::{
+  entities_info::NodeEntitiesInfo,
+  gid::Gid,
+  names::{ActionTypeName, MessageTypeName, NodeName},
+};
+
+/// A change in the ROS 2 graph.
+///
+/// Received from [`Node::graph_events`](crate::Node::graph_events).
+///
+/// Topic names are fully qualified ROS names, e.g. `/chatter`. The `node` of a
+/// Publisher or Subscription is `None`, if its Node was not known from ROS 2
+/// Discovery at the time of the event.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum GraphEvent {
+  NodeAppeared(NodeName),
+  NodeDisappeared(NodeName),
+  PublisherDiscovered {
+    topic: String,
+    type_name: MessageTypeName,
+    node: Option<NodeName>,
+  },
+  PublisherLost {
+    topic: String,
+    type_name: MessageTypeName,
+    node: Option<NodeName>,
+  },
+  SubscriptionDiscovered {
+    topic: String,
+    type_name: MessageTypeName,
+    node: Option<NodeName>,
+  },
+  SubscriptionLost {
+    topic: String,
+    type_name: MessageTypeName,
+    node: Option<NodeName>,
+  },
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum EndpointKind {
+  Publisher,
+  Subscription,
+}
+
+/// The Publishers and Subscriptions of all Nodes in one Context, with their
+/// DDS Topic names. DDS Discovery does not report these, because they are in
+/// our own DomainParticipant.
+pub(crate) type LocalEndpoints = Arc<Mutex<BTreeMap<GUID, (EndpointKind, String)>>>;
+
+/// Keeps an endpoint in [`LocalEndpoints`] until the endpoint is dropped.
+pub(crate) struct LocalEndpointRegistration {
+  endpoints: Weak<Mutex<BTreeMap<GUID, (EndpointKind, String)>>>,
+  guid: GUID,
+}
+
+impl LocalEndpointRegistration {
+  pub fn new(
+    endpoints: &LocalEndpoints,
+    kind: EndpointKind,
+    guid: GUID,
+    dds_topic_name: String,
+  ) -> LocalEndpointRegistration {
+    endpoints
+      .lock()
+      .unwrap()
+      .insert(guid, (kind, dds_topic_name));
+    LocalEndpointRegistration {
+      endpoints: Arc::downgrade(endpoints),
+      guid,
+    }
+  }
+}
+
+impl Drop for LocalEndpointRegistration {
+  fn drop(&mut self) {
+    // The Context may be gone already.
+    if let Some(endpoints) = self.endpoints.upgrade() {
+      endpoints.lock().unwrap().remove(&self.guid);
+    }
+  }
+}
+
+struct RemoteEndpoint {
+  kind: EndpointKind,
+  topic: String,
+  type_name: MessageTypeName,
+}
+
+/// Remembers the remote ROS Publishers and Subscriptions seen so far, so that
+/// repeated discovery data does not produce duplicate events.
+#[derive(Default)]
+pub(crate) struct GraphTracker {
+  endpoints: BTreeMap<GUID, RemoteEndpoint>,
+}
+
+impl GraphTracker {
+  /// A DomainParticipant has replaced its list of Nodes `old` with `new`.
+  pub fn nodes_updated(old: &[NodeEntitiesInfo], new: &[NodeEntitiesInfo]) -> Vec<GraphEvent> {
+    let old_names: Vec<NodeName> = old.iter().map(NodeEntitiesInfo::node_name).collect();
+    let new_names: Vec<NodeName> = new.iter().map(NodeEntitiesInfo::node_name).collect();
+
+    let disappeared = old_names
+      .iter()
+      .filter(|n| !new_names.contains(n))
+      .map(|n| GraphEvent::NodeDisappeared(n.clone()));
+    let appeared = new_names
+      .iter()
+      .filter(|n| !old_names.contains(n))
+      .map(|n| GraphEvent::NodeAppeared(n.clone()));
+    disappeared.chain(appeared).collect()
+  }
+
+  /// A remote DDS Reader or Writer was discovered. Returns `None` if it was
+  /// already known, or if it is not a ROS Subscription or Publisher.
+  pub fn endpoint_discovered(
+    &mut self,
+    kind: EndpointKind,
+    guid: GUID,
+    dds_topic_name: &str,
+    dds_type_name: &str,
+    nodes: &BTreeMap<Gid, Vec<NodeEntitiesInfo>>,
+  ) -> Option<GraphEvent> {
+    if self.endpoints.contains_key(&guid) {
+      return None; // e.g. QoS update
+    }
+    let topic = ros_topic_name(dds_topic_name)?;
+    let type_name = MessageTypeName::from_dds_msg_type(dds_type_name)?;
+    let endpoint = RemoteEndpoint {
+      kind,
+      topic,
+      type_name,
+    };
+    let event = endpoint.event(true, owner_node(kind, guid, nodes));
+    self.endpoints.insert(guid, endpoint);
+    Some(event)
+  }
+
+  /// Number of known remote endpoints of `kind` on ROS Topic `topic`.
+  pub fn count(&self, kind: EndpointKind, topic: &str) -> usize {
+    self
+      .endpoints
+      .values()
+      .filter(|e| e.kind == kind && e.topic == topic)
+      .count()
+  }
+
+  /// A remote DDS Reader or Writer was lost. Returns `None` if we did not
+  /// report it as discovered.
+  pub fn endpoint_lost(
+    &mut self,
+    guid: GUID,
+    nodes: &BTreeMap<Gid, Vec<NodeEntitiesInfo>>,
+  ) -> Option<GraphEvent> {
+    self
+      .endpoints
+      .remove(&guid)
+      .map(|endpoint| endpoint.event(false, owner_node(endpoint.kind, guid, nodes)))
+  }
+}
+
+impl RemoteEndpoint {
+  fn event(&self, discovered: bool, node: Option<NodeName>) -> GraphEvent {
+    let (topic, type_name) = (self.topic.clone(), self.type_name.clone());
+    match (self.kind, discovered) {
+      (EndpointKind::Publisher, true) => GraphEvent::PublisherDiscovered {
+        topic,
+        type_name,
+        node,
+      },
+      (EndpointKind::Publisher, false) => GraphEvent::PublisherLost {
+        topic,
+        type_name,
+        node,
+      },
+      (EndpointKind::Subscription, true) => GraphEvent::SubscriptionDiscovered {
+        topic,
+        type_name,
+        node,
+      },
+      (EndpointKind::Subscription, false) => GraphEvent::SubscriptionLost {
+        topic,
+        type_name,
+        node,
+      },
+    }
+  }
+}
+
+fn owner_node(
+  kind: EndpointKind,
+  guid: GUID,
+  nodes: &BTreeMap<Gid, Vec<NodeEntitiesInfo>>,
+) -> Option<NodeName> {
+  let gid = Gid::from(guid);
+  nodes
+    .values()
+    .flatten()
+    .find(|n| match kind {
+      EndpointKind::Publisher => n.has_writer(gid),
+      EndpointKind::Subscription => n.has_reader(gid),
+    })
+    .map(NodeEntitiesInfo::node_name)
+}
+
+/// ROS Topic name from DDS Topic name, e.g. `rt/chatter` becomes `/chatter`.
+/// Returns `None` for other DDS Topics, such as Service request/response.
+pub(crate) fn ros_topic_name(dds_topic_name: &str) -> Option<String> {
+  dds_topic_name
+    .strip_prefix("rt/")
+    .map(|name| format!("/{name}"))
+}
+
+// DDS Topics of an Action: prefix and suffix around the Action name, and what
+// to strip off the DDS type name to get the Action type name. `None` means
+// the type is not specific to the Action.
+const ACTION_PARTS: &[(&str, &str, Option<&str>)] = &[
+  ("rt/", "/_action/feedback", Some("_FeedbackMessage")),
+  ("rt/", "/_action/status", None),
+  (
+    "rq/",
+    "/_action/send_goalRequest",
+    Some("_SendGoal_Request"),
+  ),
+  (
+    "rq/",
+    "/_action/get_resultRequest",
+    Some("_GetResult_Request"),
+  ),
+  ("rq/", "/_action/cancel_goalRequest", None),
+];
+
+/// Recognize Actions from DDS Topic names and types. An Action is included
+/// only if all of its parts are present.
+pub(crate) fn action_names_and_types(
+  dds_topics: impl IntoIterator<Item = (String, String)>,
+) -> BTreeMap<String, Vec<ActionTypeName>> {
+  // Action name -> (indices of found parts, types)
+  let mut found: BTreeMap<String, (BTreeSet<usize>, Vec<ActionTypeName>)> = BTreeMap::new();
+
+  for (dds_topic_name, dds_type_name) in dds_topics {
+    for (i, (kind_prefix, part_suffix, type_suffix)) in ACTION_PARTS.iter().enumerate() {
+      let action_name = match dds_topic_name
+        .strip_prefix(kind_prefix)
+        .and_then(|n| n.strip_suffix(part_suffix))
+      {
+        Some(action_name) => format!("/{action_name}"),
+        None => continue,
+      };
+      let (parts, types) = found.entry(action_name).or_default();
+      parts.insert(i);
+      if let Some(type_suffix) = type_suffix {
+        let action_type = MessageTypeName::from_dds_msg_type(&dds_type_name).and_then(|t| {
+          t.type_name()
+            .strip_suffix(type_suffix)
+            .map(|a| ActionTypeName::new(t.package_name(), a))
+        });
+        match action_type {
+          Some(action_type) if !types.contains(&action_type) => types.push(action_type),
+          _ => {}
+        }
+      }
+    }
+  }
+
+  found
+    .into_iter()
+    .filter(|(_, (parts, _))| parts.len() == ACTION_PARTS.len())
+    .map(|(name, (_, types))| (name, types))
+    .collect()
+}
+
+// -------------------------------------------------------------------------------------
+
+#[test]
+fn graph_tracker_reports_changes_once() {
+  let talker = NodeName::new("/graph_test", "talker").unwrap();
+  let listener = NodeName::new("/graph_test", "listener").unwrap();
+  let writer = GUID::from_bytes([1; 16]);
+  let reader = GUID::from_bytes([2; 16]);
+  let string_type = MessageTypeName::new("std_msgs", "String");
+
+  // Nodes appear and disappear
+  let mut talker_info = NodeEntitiesInfo::new(talker.clone());
+  talker_info.add_writer(Gid::from(writer));
+  let listener_info = NodeEntitiesInfo::new(listener.clone());
+  assert_eq!(
+    GraphTracker::nodes_updated(&[], &[talker_info.clone()]),
+    vec![GraphEvent::NodeAppeared(talker.clone())]
+  );
+  assert_eq!(
+    GraphTracker::nodes_updated(&[talker_info.clone()], &[talker_info.clone()]),
+    vec![]
+  );
+  assert_eq!(
+    GraphTracker::nodes_updated(&[talker_info.clone()], &[listener_info]),
+    vec![
+      GraphEvent::NodeDisappeared(talker.clone()),
+      GraphEvent::NodeAppeared(listener),
+    ]
+  );
+
+  // Publisher appears, and is reported only once
+  let mut nodes = BTreeMap::new();
+  nodes.insert(Gid::from(GUID::from_bytes([9; 16])), vec![talker_info]);
+  let mut tracker = GraphTracker::default();
+  let discover_writer = |tracker: &mut GraphTracker| {
+    tracker.endpoint_discovered(
+      EndpointKind::Publisher,
+      writer,
+      "rt/graph_test/chatter",
+      "std_msgs::msg::dds_::String_",
+      &nodes,
+    )
+  };
+  assert_eq!(
+    discover_writer(&mut tracker),
+    Some(GraphEvent::PublisherDiscovered {
+      topic: "/graph_test/chatter".to_string(),
+      type_name: string_type.clone(),
+      node: Some(talker.clone()),
+    })
+  );
+  assert_eq!(discover_writer(&mut tracker), None);
+  assert_eq!(
+    tracker.count(EndpointKind::Publisher, "/graph_test/chatter"),
+    1
+  );
+  assert_eq!(
+    tracker.count(EndpointKind::Subscription, "/graph_test/chatter"),
+    0
+  );
+
+  // Node of a Subscription is not known
+  assert_eq!(
+    tracker.endpoint_discovered(
+      EndpointKind::Subscription,
+      reader,
+      "rt/graph_test/chatter",
+      "std_msgs::msg::dds_::String_",
+      &nodes,
+    ),
+    Some(GraphEvent::SubscriptionDiscovered {
+      topic: "/graph_test/chatter".to_string(),
+      type_name: string_type.clone(),
+      node: None,
+    })
+  );
+
+  // Non-ROS Topics are not reported
+  assert_eq!(
+    tracker.endpoint_discovered(
+      EndpointKind::Subscription,
+      GUID::from_bytes([3; 16]),
+      "rq/graph_test/serviceRequest",
+      "example_interfaces::srv::dds_::AddTwoInts_Request_",
+      &nodes,
+    ),
+    None
+  );
+  assert_eq!(
+    tracker.endpoint_lost(GUID::from_bytes([3; 16]), &nodes),
+    None
+  );
+
+  // Publisher disappears
+  assert_eq!(
+    tracker.endpoint_lost(writer, &nodes),
+    Some(GraphEvent::PublisherLost {
+      topic: "/graph_test/chatter".to_string(),
+      type_name: string_type,
+      node: Some(talker),
+    })
+  );
+  assert_eq!(tracker.endpoint_lost(writer, &nodes), None);
+}
+
+#[test]
+fn local_endpoint_is_forgotten_on_drop() {
+  let endpoints: LocalEndpoints = Arc::new(Mutex::new(BTreeMap::new()));
+  let publisher = LocalEndpointRegistration::new(
+    &endpoints,
+    EndpointKind::Publisher,
+    GUID::from_bytes([1; 16]),
+    "rt/scan".to_string(),
+  );
+  let _subscription = LocalEndpointRegistration::new(
+    &endpoints,
+    EndpointKind::Subscription,
+    GUID::from_bytes([2; 16]),
+    "rt/scan".to_string(),
+  );
+  assert_eq!(endpoints.lock().unwrap().len(), 2);
+
+  drop(publisher);
+  let remaining: Vec<EndpointKind> = endpoints
+    .lock()
+    .unwrap()
+    .values()
+    .map(|(kind, _)| *kind)
+    .collect();
+  assert_eq!(remaining, vec![EndpointKind::Subscription]);
+}
+
+#[test]
+fn actions_are_recognized_from_topics() {
+  let topics = |names: &[(&str, &str)]| {
+    names
+      .iter()
+      .map(|(n, t)| (n.to_string(), t.to_string()))
+      .collect::<Vec<_>>()
+  };
+  let fibonacci_parts = [
+    (
+      "rt/fibonacci/_action/feedback",
+      "example_interfaces::action::dds_::Fibonacci_FeedbackMessage_",
+    ),
+    (
+      "rt/fibonacci/_action/status",
+      "action_msgs::msg::dds_::GoalStatusArray_",
+    ),
+    (
+      "rq/fibonacci/_action/send_goalRequest",
+      "example_interfaces::action::dds_::Fibonacci_SendGoal_Request_",
+    ),
+    (
+      "rq/fibonacci/_action/get_resultRequest",
+      "example_interfaces::action::dds_::Fibonacci_GetResult_Request_",
+    ),
+    (
+      "rq/fibonacci/_action/cancel_goalRequest",
+      "action_msgs::srv::dds_::CancelGoal_Request_",
+    ),
+    ("rt/chatter", "std_msgs::msg::dds_::String_"),
+  ];
+
+  let actions = action_names_and_types(topics(&fibonacci_parts));
+  assert_eq!(actions.len(), 1);
+  assert_eq!(
+    actions.get("/fibonacci"),
+    Some(&vec![ActionTypeName::new(
+      "example_interfaces",
+      "Fibonacci"
+    )])
+  );
+
+  // Partially discovered, e.g. status is missing
+  let partial: Vec<_> = fibonacci_parts
+    .iter()
+    .filter(|(n, _)| !n.ends_with("status"))
+    .copied()
+    .collect();
+  assert!(action_names_and_types(topics(&partial)).is_empty());
+}
@@ -0,0 +1,15 @@
+use serde::{Deserialize, Serialize};
+
+use crate::{parameters, Message};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DescribeParametersRequest {
+  pub names: Vec<String>,
+}
+impl Message for DescribeParametersRequest {}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DescribeParametersResponse {
+  pub descriptors: Vec<parameters::raw::ParameterDescriptor>,
+}
+impl Message for DescribeParametersResponse {}
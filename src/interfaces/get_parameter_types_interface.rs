@@ -0,0 +1,15 @@
+use serde::{Deserialize, Serialize};
+
+use crate::Message;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GetParameterTypesRequest {
+  pub names: Vec<String>,
+}
+impl Message for GetParameterTypesRequest {}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GetParameterTypesResponse {
+  pub types: Vec<u8>,
+}
+impl Message for GetParameterTypesResponse {}
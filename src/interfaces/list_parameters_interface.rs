@@ -2,22 +2,25 @@ use serde::{Deserialize, Serialize};
 
 use crate::Message;
 
-#[allow(non_snake_case)]
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ListParametersRequest {
-  pub DEPTH_RECURSIVE: u64,
   pub prefixes: Vec<String>,
   pub depth: u64,
 }
 impl Message for ListParametersRequest {}
 
+impl ListParametersRequest {
+  /// Value of `depth` to list parameters at all depths.
+  pub const DEPTH_RECURSIVE: u64 = 0;
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ListParametersResponse {
   pub result: ListParametersResult,
 }
 impl Message for ListParametersResponse {}
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, Default, PartialEq, Eq)]
 pub struct ListParametersResult {
   pub names: Vec<String>,
   pub prefixes: Vec<String>,
@@ -1,9 +1,15 @@
 mod basic_types_interface;
+mod describe_parameters_interface;
+mod get_parameter_types_interface;
 mod get_parameters_interface;
 mod list_parameters_interface;
 //mod marker_interface;
+mod set_parameters_interface;
 
 pub use basic_types_interface::*;
+pub use describe_parameters_interface::*;
+pub use get_parameter_types_interface::*;
 pub use get_parameters_interface::*;
 pub use list_parameters_interface::*;
 //pub use marker_interface::*;
+pub use set_parameters_interface::*;
@@ -0,0 +1,27 @@
+use serde::{Deserialize, Serialize};
+
+use crate::{parameters, Message};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SetParametersRequest {
+  pub parameters: Vec<parameters::raw::Parameter>,
+}
+impl Message for SetParametersRequest {}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SetParametersResponse {
+  pub results: Vec<parameters::raw::SetParametersResult>,
+}
+impl Message for SetParametersResponse {}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SetParametersAtomicallyRequest {
+  pub parameters: Vec<parameters::raw::Parameter>,
+}
+impl Message for SetParametersAtomicallyRequest {}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SetParametersAtomicallyResponse {
+  pub result: parameters::raw::SetParametersResult,
+}
+impl Message for SetParametersAtomicallyResponse {}
@@ -44,6 +44,15 @@
 //!   // Uncomment this to execute until interrupted.
 //!   // --> smol::block_on( subscription_stream );
 //! ```
+//!
+//! # Async runtimes
+//!
+//! The async API does not depend on any particular executor. Only timers
+//! (e.g. [`Client::async_call_service_timeout`]) need runtime support. These
+//! use `async-io` by default (feature `rt-smol`). With
+//! `default-features = false, features = ["rt-tokio"]`, Tokio timers are used
+//! instead, and e.g. `tokio::spawn(node.spinner()?.spin())` works without
+//! pulling in smol.
 
 #[macro_use]
 extern crate lazy_static;
@@ -63,25 +72,40 @@ pub mod interfaces;
 
 /// ROS 2 Action machinery
 pub mod action;
+mod clock;
 pub mod entities_info;
 mod gid;
+mod graph;
 pub mod log;
 pub mod message;
 pub mod message_info;
 pub mod names;
+mod parameter_server;
 pub mod parameters;
 #[doc(hidden)]
 pub mod pubsub;
+pub mod qos;
+mod ros_args;
+pub mod rosgraph_msgs;
+mod runtime;
 pub mod service;
+#[cfg(test)]
+mod test_util;
+mod wait_set;
 mod wide_string;
+mod xcdr2;
 
 #[doc(hidden)]
 pub(crate) mod node;
 
 // Re-exports from crate root to simplify usage
 #[doc(inline)]
+pub use clock::Clock;
+#[doc(inline)]
 pub use context::*;
 #[doc(inline)]
+pub use graph::GraphEvent;
+#[doc(inline)]
 pub use message::Message;
 #[doc(inline)]
 pub use names::{ActionTypeName, MessageTypeName, Name, NodeName, ServiceTypeName};
@@ -90,21 +114,25 @@ pub use message_info::MessageInfo;
 #[doc(inline)]
 pub use node::*;
 #[doc(inline)]
+pub use parameter_server::ParameterServer;
+#[doc(inline)]
 pub use pubsub::*;
 #[doc(inline)]
 pub use service::{AService, Client, Server, Service, ServiceMapping};
 #[doc(inline)]
 pub use action::{Action, ActionTypes};
 #[doc(inline)]
+pub use wait_set::{EntityId, EntityKey, ReadyEntity, WaitSet};
+#[doc(inline)]
 pub use wide_string::WString;
 
 /// Module for stuff we do not want to export from top level;
 pub mod ros2 {
-  pub use rustdds::{qos::policy, Duration, QosPolicies, QosPolicyBuilder, Timestamp};
+  pub use rustdds::{qos::policy, Duration, Key, Keyed, QosPolicies, QosPolicyBuilder, Timestamp};
   //TODO: re-export RustDDS error types until ros2-client defines its own
   pub use rustdds::dds::{CreateError, ReadError, WaitError, WriteError};
 
-  pub use crate::log::LogLevel;
+  pub use crate::{log::LogLevel, qos};
   // TODO: What to do about SecurityError (exists based on feature "security")
   pub use crate::names::Name; // import Name as ros2::Name if there is clash
                               // otherwise
@@ -56,6 +56,25 @@ impl Log {
   pub fn get_line(&self) -> u32 {
     self.line
   }
+
+  /// Corresponding level in the `log` crate.
+  ///
+  /// `log` has no Fatal level, so Fatal maps to `Error`. Levels below Debug
+  /// map to `Trace`.
+  pub fn log_level(&self) -> ::log::Level {
+    match self.level {
+      l if l >= Log::ERROR => ::log::Level::Error,
+      l if l >= Log::WARN => ::log::Level::Warn,
+      l if l >= Log::INFO => ::log::Level::Info,
+      l if l >= Log::DEBUG => ::log::Level::Debug,
+      _ => ::log::Level::Trace,
+    }
+  }
+
+  /// Emit this message via the `log` crate. The `name` is used as log target.
+  pub fn to_log(&self) {
+    ::log::log!(target: self.name.as_str(), self.log_level(), "{}", self.msg);
+  }
 }
 
 #[repr(u8)]
@@ -68,4 +87,126 @@ pub enum LogLevel {
   Debug = 10,
 }
 
+impl From<LogLevel> for ::log::Level {
+  fn from(level: LogLevel) -> ::log::Level {
+    match level {
+      LogLevel::Fatal | LogLevel::Error => ::log::Level::Error,
+      LogLevel::Warn => ::log::Level::Warn,
+      LogLevel::Info => ::log::Level::Info,
+      LogLevel::Debug => ::log::Level::Debug,
+    }
+  }
+}
+
 //impl From<u8> for Level
+
+// -------------------------------------------------------------------------------------
+
+#[cfg(test)]
+fn test_entry(level: LogLevel, name: &str) -> Log {
+  Log {
+    timestamp: Timestamp::ZERO,
+    level: level as u8,
+    name: name.to_string(),
+    msg: format!("{level:?} message"),
+    file: "log.rs".to_string(),
+    function: "test".to_string(),
+    line: 1,
+  }
+}
+
+#[test]
+fn log_entries_map_to_log_crate_levels() {
+  for level in [
+    LogLevel::Fatal,
+    LogLevel::Error,
+    LogLevel::Warn,
+    LogLevel::Info,
+    LogLevel::Debug,
+  ] {
+    assert_eq!(
+      test_entry(level, "test_node").log_level(),
+      ::log::Level::from(level)
+    );
+  }
+  assert_eq!(
+    test_entry(LogLevel::Fatal, "x").log_level(),
+    ::log::Level::Error
+  );
+  let below_debug = Log {
+    level: 5,
+    ..test_entry(LogLevel::Debug, "x")
+  };
+  assert_eq!(below_debug.log_level(), ::log::Level::Trace);
+}
+
+#[test]
+fn log_entry_is_emitted_with_name_as_target() {
+  let logger = match crate::test_util::test_logger() {
+    Some(logger) => logger,
+    None => return, // Some other logger is installed. Nothing to check.
+  };
+  test_entry(LogLevel::Warn, "test_log_target").to_log();
+  assert_eq!(
+    logger.take("test_log_target"),
+    vec![(::log::Level::Warn, "Warn message".to_string())]
+  );
+}
+
+#[test]
+#[ignore]
+fn rosout_is_forwarded_to_log() {
+  use std::time::Duration;
+
+  use futures::FutureExt;
+
+  use crate::{test_util::test_logger, Context, NodeName, NodeOptions};
+
+  let logger = match test_logger() {
+    Some(logger) => logger,
+    None => return, // Some other logger is installed. Nothing to check.
+  };
+  let context = Context::new().unwrap();
+  let talker = context
+    .new_node(
+      NodeName::new("/rosout_test", "talker").unwrap(),
+      NodeOptions::new().enable_rosout(true),
+    )
+    .unwrap();
+  let listener = context
+    .new_node(
+      NodeName::new("/rosout_test", "listener").unwrap(),
+      NodeOptions::new().read_rosout(true),
+    )
+    .unwrap();
+
+  let forward = listener.rosout_to_log().fuse();
+  let check = async {
+    for _ in 0..50 {
+      talker.rosout_raw(
+        Timestamp::now(),
+        LogLevel::Error,
+        "test_rosout_forward",
+        "Forwarded",
+        "log.rs",
+        "test",
+        1,
+      );
+      crate::runtime::sleep(Duration::from_millis(100)).await;
+      let records = logger.take("test_rosout_forward");
+      if let Some(record) = records.into_iter().next() {
+        return Some(record);
+      }
+    }
+    None
+  }
+  .fuse();
+  futures::pin_mut!(forward, check);
+  let record = smol::block_on(async {
+    futures::select! {
+      _ = forward => None,
+      record = check => record,
+    }
+  });
+  assert_eq!(record, Some((::log::Level::Error, "Forwarded".to_string())));
+}
@@ -1,5 +1,8 @@
-//! Defines [`Message`] trait
+//! Defines [`Message`] trait, and type descriptions for computing type hashes
+use std::fmt::Write;
+
 use serde::{de::DeserializeOwned, Serialize};
+use sha2::{Digest, Sha256};
 
 /// Trait to ensure Messages can be (de)serialized
 pub trait Message: Serialize + DeserializeOwned {}
@@ -18,3 +21,180 @@ impl Message for u32 {}
 impl Message for u64 {}
 
 impl<T: Message> Message for Vec<T> {}
+
+/// Type ids of [`FieldType`], as in
+/// [FieldType.msg](https://github.com/ros2/rcl_interfaces/blob/rolling/type_description_interfaces/msg/FieldType.msg)
+///
+/// Array, bounded sequence and unbounded sequence types are the element type id
+/// plus [`ARRAY`](field_type::ARRAY), [`BOUNDED_SEQUENCE`](field_type::BOUNDED_SEQUENCE)
+/// or [`UNBOUNDED_SEQUENCE`](field_type::UNBOUNDED_SEQUENCE), respectively.
+pub mod field_type {
+  pub const NOT_SET: u8 = 0;
+  pub const NESTED_TYPE: u8 = 1;
+  pub const INT8: u8 = 2;
+  pub const UINT8: u8 = 3;
+  pub const INT16: u8 = 4;
+  pub const UINT16: u8 = 5;
+  pub const INT32: u8 = 6;
+  pub const UINT32: u8 = 7;
+  pub const INT64: u8 = 8;
+  pub const UINT64: u8 = 9;
+  pub const FLOAT: u8 = 10;
+  pub const DOUBLE: u8 = 11;
+  pub const LONG_DOUBLE: u8 = 12;
+  pub const CHAR: u8 = 13;
+  pub const WCHAR: u8 = 14;
+  pub const BOOLEAN: u8 = 15;
+  pub const BYTE: u8 = 16;
+  pub const STRING: u8 = 17;
+  pub const WSTRING: u8 = 18;
+  pub const FIXED_STRING: u8 = 19;
+  pub const FIXED_WSTRING: u8 = 20;
+  pub const BOUNDED_STRING: u8 = 21;
+  pub const BOUNDED_WSTRING: u8 = 22;
+
+  pub const ARRAY: u8 = 48;
+  pub const BOUNDED_SEQUENCE: u8 = 96;
+  pub const UNBOUNDED_SEQUENCE: u8 = 144;
+}
+
+/// Type of a message field, see [`field_type`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FieldType {
+  pub type_id: u8,
+  /// Array size or sequence bound, otherwise 0
+  pub capacity: u64,
+  /// Bound of (w)string, or its elements, otherwise 0
+  pub string_capacity: u64,
+  /// e.g. `geometry_msgs/msg/Point`, if this is a nested type
+  pub nested_type_name: String,
+}
+
+impl FieldType {
+  /// A field type without bounds, which is not a nested type.
+  pub fn new(type_id: u8) -> FieldType {
+    FieldType {
+      type_id,
+      capacity: 0,
+      string_capacity: 0,
+      nested_type_name: String::new(),
+    }
+  }
+}
+
+/// A named field of a message.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Field {
+  pub name: String,
+  pub field_type: FieldType,
+}
+
+/// Description of a single type, e.g. a type referred to by a nested field.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct IndividualTypeDescription {
+  /// e.g. `std_msgs/msg/String`
+  pub type_name: String,
+  pub fields: Vec<Field>,
+}
+
+/// Description of a message type, as in
+/// [TypeDescription.msg](https://github.com/ros2/rcl_interfaces/blob/rolling/type_description_interfaces/msg/TypeDescription.msg),
+/// but without the name of the type itself.
+///
+/// `referenced_type_descriptions` must describe all types nested in the fields,
+/// recursively.
+#[derive(Clone, Debug, PartialEq, Eq, Default)]
+pub struct TypeDescription {
+  pub fields: Vec<Field>,
+  pub referenced_type_descriptions: Vec<IndividualTypeDescription>,
+}
+
+/// Compute the RIHS01 type hash, as defined in
+/// [REP 2016](https://ros.org/reps/rep-2016.html), of the type `type_name`,
+/// e.g. `std_msgs/msg/String`. See also [`crate::MessageTypeName::rihs01_hash`].
+///
+/// The result is in string form, e.g. `RIHS01_df668c...`.
+pub fn rihs01_hash(type_name: &str, description: &TypeDescription) -> String {
+  // The hash is computed over a specific JSON representation.
+  let mut referenced = description.referenced_type_descriptions.clone();
+  referenced.sort_by(|a, b| a.type_name.cmp(&b.type_name));
+
+  let mut json = String::from("{\"type_description\": ");
+  write_individual_json(&mut json, type_name, &description.fields);
+  json.push_str(", \"referenced_type_descriptions\": [");
+  for (i, r) in referenced.iter().enumerate() {
+    if i > 0 {
+      json.push_str(", ");
+    }
+    write_individual_json(&mut json, &r.type_name, &r.fields);
+  }
+  json.push_str("]}");
+
+  let mut hash = String::from("RIHS01_");
+  for byte in Sha256::digest(json.as_bytes()) {
+    write!(hash, "{byte:02x}").unwrap();
+  }
+  hash
+}
+
+fn write_individual_json(json: &mut String, type_name: &str, fields: &[Field]) {
+  write!(
+    json,
+    "{{\"type_name\": {}, \"fields\": [",
+    json_string(type_name)
+  )
+  .unwrap();
+  for (i, field) in fields.iter().enumerate() {
+    if i > 0 {
+      json.push_str(", ");
+    }
+    let t = &field.field_type;
+    write!(
+      json,
+      "{{\"name\": {}, \"type\": {{\"type_id\": {}, \"capacity\": {}, \
+       \"string_capacity\": {}, \"nested_type_name\": {}}}}}",
+      json_string(&field.name),
+      t.type_id,
+      t.capacity,
+      t.string_capacity,
+      json_string(&t.nested_type_name),
+    )
+    .unwrap();
+  }
+  json.push_str("]}");
+}
+
+fn json_string(s: &str) -> String {
+  let mut quoted = String::from("\"");
+  for c in s.chars() {
+    match c {
+      '"' => quoted.push_str("\\\""),
+      '\\' => quoted.push_str("\\\\"),
+      c if (c as u32) < 0x20 => write!(quoted, "\\u{:04x}", c as u32).unwrap(),
+      c => quoted.push(c),
+    }
+  }
+  quoted.push('"');
+  quoted
+}
+
+// -------------------------------------------------------------------------------------
+
+#[test]
+fn rihs01_hash_of_standard_messages() {
+  let string = TypeDescription {
+    fields: vec![Field {
+      name: "data".to_string(),
+      field_type: FieldType::new(field_type::STRING),
+    }],
+    referenced_type_descriptions: vec![],
+  };
+  assert_eq!(
+    rihs01_hash("std_msgs/msg/String", &string),
+    "RIHS01_df668c740482bbd48fb39d76a70dfd4bd59db1288021743503259e948f6b1a18"
+  );
+  assert_eq!(
+    crate::MessageTypeName::new("std_msgs", "String").rihs01_hash(&string),
+    "RIHS01_df668c740482bbd48fb39d76a70dfd4bd59db1288021743503259e948f6b1a18"
+  );
+}
@@ -1,5 +1,7 @@
 use rustdds::{rpc::SampleIdentity, *};
 
+use crate::gid::Gid;
+
 /// Message metadata
 #[derive(Debug, Clone)]
 pub struct MessageInfo {
@@ -8,6 +10,7 @@ pub struct MessageInfo {
   sequence_number: SequenceNumber,
   publisher: GUID,
   related_sample_identity: Option<SampleIdentity>,
+  instance_state: InstanceState,
 }
 
 impl MessageInfo {
@@ -23,6 +26,17 @@ impl MessageInfo {
     self.publisher
   }
 
+  /// Gid of the Publisher, same as [`Publisher::gid`](crate::Publisher::gid)
+  /// on the sending side.
+  pub fn writer_gid(&self) -> Gid {
+    self.publisher.into()
+  }
+
+  /// Sequence number assigned to the message by its Publisher.
+  pub fn publication_sequence_number(&self) -> SequenceNumber {
+    self.sequence_number
+  }
+
   pub fn sample_identity(&self) -> rustdds::rpc::SampleIdentity {
     rustdds::rpc::SampleIdentity {
       writer_guid: self.writer_guid(),
@@ -33,6 +47,15 @@ impl MessageInfo {
   pub fn related_sample_identity(&self) -> Option<SampleIdentity> {
     self.related_sample_identity
   }
+
+  /// State of the instance this message belongs to.
+  ///
+  /// Only keyed Topics have instances that can be disposed or unregistered
+  /// (see [`KeyedPublisher`](crate::KeyedPublisher)). On other Topics this is
+  /// always `Alive`.
+  pub fn instance_state(&self) -> InstanceState {
+    self.instance_state
+  }
 }
 
 impl From<&SampleInfo> for MessageInfo {
@@ -43,6 +66,7 @@ impl From<&SampleInfo> for MessageInfo {
       sequence_number: sample_info.sample_identity().sequence_number,
       publisher: sample_info.publication_handle(), // DDS has an odd name for this
       related_sample_identity: sample_info.related_sample_identity(),
+      instance_state: sample_info.instance_state(),
     }
   }
 }
@@ -55,6 +79,7 @@ impl<M> From<&rustdds::no_key::DeserializedCacheChange<M>> for MessageInfo {
       sequence_number: dcc.sequence_number,
       publisher: dcc.writer_guid(),
       related_sample_identity: dcc.related_sample_identity(),
+      instance_state: InstanceState::Alive,
     }
   }
 }
@@ -4,7 +4,7 @@
 //! * action types, e.g. `turtlesim/RotateAbsolute`
 //! *
 
-use std::fmt;
+use std::{fmt, str::FromStr};
 
 // TODO:
 // Conform fully to https://design.ros2.org/articles/topic_and_service_names.html
@@ -244,6 +244,19 @@ impl Name {
   }
 }
 
+/// Same as [`Name::parse`].
+impl FromStr for Name {
+  type Err = NameError;
+
+  fn from_str(s: &str) -> Result<Name, NameError> {
+    Name::parse(s)
+  }
+}
+
+/// The Name in string form, e.g. `/turtle1/cmd_vel`. Relative names do not
+/// begin with a slash, and are not resolved against any namespace.
+///
+/// This is the inverse of [`Name::parse`].
 impl fmt::Display for Name {
   fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
     if self.absolute {
@@ -263,7 +276,7 @@ impl fmt::Display for Name {
 /// Note that this is not for naming Topics, but data types of Topics.
 ///
 /// See [Names](https://wiki.ros.org/Names) Section 1.2 Package Resource Names.
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, PartialEq, Eq)]
 pub struct MessageTypeName {
   prefix: String, // typically "msg", but may be "action". What should this part be called?
   //TODO: String is strictly UTF-8, but ROS2 uses just byte strings that are recommended to be
@@ -273,8 +286,8 @@ pub struct MessageTypeName {
 }
 
 impl MessageTypeName {
+  /// Does not check the names. See [`Self::try_new`].
   pub fn new(package_name: &str, type_name: &str) -> Self {
-    //TODO: Ensure parameters have no leading/trailing slashes
     MessageTypeName {
       prefix: "msg".to_string(),
       ros2_package_name: package_name.to_owned(),
@@ -282,6 +295,15 @@ impl MessageTypeName {
     }
   }
 
+  /// Checked version of [`Self::new`].
+  ///
+  /// `package_name` must be a valid ROS package name, e.g. `std_msgs`, and
+  /// `type_name` a CamelCase identifier, e.g. `String`.
+  pub fn try_new(package_name: &str, type_name: &str) -> Result<Self, NameError> {
+    check_type_name_parts(package_name, type_name)?;
+    Ok(Self::new(package_name, type_name))
+  }
+
   pub(crate) fn new_prefix(package_name: &str, type_name: &str, prefix: String) -> Self {
     MessageTypeName {
       prefix,
@@ -304,12 +326,59 @@ impl MessageTypeName {
       self.ros2_package_name.clone() + "/" + &self.prefix + "/dds_/" + &self.ros2_type_name + "_",
     )
   }
+
+  /// RIHS01 type hash of this type, given a description of its fields. See
+  /// [`crate::message::rihs01_hash`].
+  pub fn rihs01_hash(&self, description: &crate::message::TypeDescription) -> String {
+    let type_name = format!(
+      "{}/{}/{}",
+      self.ros2_package_name, self.prefix, self.ros2_type_name
+    );
+    crate::message::rihs01_hash(&type_name, description)
+  }
+
+  /// Inverse of [`Self::dds_msg_type`], e.g. `std_msgs::msg::dds_::String_`
+  /// becomes `std_msgs/String`. Returns `None` if the name is not in ROS 2
+  /// format.
+  pub(crate) fn from_dds_msg_type(dds_type_name: &str) -> Option<Self> {
+    match dds_type_name.split("::").collect::<Vec<_>>().as_slice() {
+      [package_name, prefix, "dds_", type_name] if !package_name.is_empty() => type_name
+        .strip_suffix('_')
+        .filter(|t| !t.is_empty())
+        .map(|t| Self::new_prefix(package_name, t, prefix.to_string())),
+      _ => None,
+    }
+  }
 }
 
 fn slash_to_colons(s: String) -> String {
   s.replace('/', "::")
 }
 
+// Package names follow REP 144: lowercase alphanumerics and underscores,
+// starting with a letter, no repeated or trailing underscores.
+// Type names are CamelCase: alphanumerics, starting with an uppercase letter.
+fn check_type_name_parts(package_name: &str, type_name: &str) -> Result<(), NameError> {
+  if package_name.is_empty() || type_name.is_empty() {
+    return Err(NameError::Empty);
+  }
+
+  let package_ok = package_name.starts_with(|c: char| c.is_ascii_lowercase())
+    && package_name
+      .chars()
+      .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_')
+    && !package_name.contains("__")
+    && !package_name.ends_with('_');
+  let type_ok = type_name.starts_with(|c: char| c.is_ascii_uppercase())
+    && type_name.chars().all(|c| c.is_ascii_alphanumeric());
+
+  if package_ok && type_ok {
+    Ok(())
+  } else {
+    Err(NameError::BadChar)
+  }
+}
+
 /// Similar to [`MessageTypeName`], but names a Service type.
 #[derive(Clone, Debug)]
 pub struct ServiceTypeName {
@@ -318,6 +387,7 @@ pub struct ServiceTypeName {
 }
 
 impl ServiceTypeName {
+  /// Does not check the names. See [`Self::try_new`].
   pub fn new(package_name: &str, type_name: &str) -> Self {
     ServiceTypeName {
       prefix: "srv".to_string(),
@@ -325,6 +395,12 @@ impl ServiceTypeName {
     }
   }
 
+  /// Checked version of [`Self::new`], see [`MessageTypeName::try_new`].
+  pub fn try_new(package_name: &str, type_name: &str) -> Result<Self, NameError> {
+    check_type_name_parts(package_name, type_name)?;
+    Ok(Self::new(package_name, type_name))
+  }
+
   pub(crate) fn new_prefix(package_name: &str, type_name: &str, prefix: String) -> Self {
     ServiceTypeName {
       prefix,
@@ -364,14 +440,21 @@ impl ServiceTypeName {
 }
 
 /// Similar to [`MessageTypeName`], but names an Action type.
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, PartialEq, Eq)]
 pub struct ActionTypeName(MessageTypeName);
 
 impl ActionTypeName {
+  /// Does not check the names. See [`Self::try_new`].
   pub fn new(package_name: &str, type_name: &str) -> Self {
     ActionTypeName(MessageTypeName::new(package_name, type_name))
   }
 
+  /// Checked version of [`Self::new`], see [`MessageTypeName::try_new`].
+  pub fn try_new(package_name: &str, type_name: &str) -> Result<Self, NameError> {
+    check_type_name_parts(package_name, type_name)?;
+    Ok(Self::new(package_name, type_name))
+  }
+
   pub fn package_name(&self) -> &str {
     self.0.package_name()
   }
@@ -444,3 +527,43 @@ fn test_name_parse() {
   assert_eq!(Name::parse("a/nn").unwrap().is_absolute(), false);
   assert_eq!(Name::parse("/a/nn").unwrap().is_absolute(), true);
 }
+
+#[test]
+fn test_name_from_str_round_trip() {
+  for n in [
+    Name::new("/", "topic").unwrap(),
+    Name::new("/turtle1", "cmd_vel").unwrap(),
+    Name::new("/a/b/c", "nn").unwrap(),
+    Name::new("", "chatter").unwrap(),
+    Name::new("ns", "chatter").unwrap(),
+  ] {
+    assert_eq!(Name::from_str(&n.to_string()).unwrap(), n);
+  }
+
+  assert_eq!(
+    "/turtle1/cmd_vel".parse::<Name>().unwrap(),
+    Name::new("/turtle1", "cmd_vel").unwrap()
+  );
+  assert!("/turtle1/cmd_vel/".parse::<Name>().is_err());
+  assert!("/turtle1//cmd_vel".parse::<Name>().is_err());
+  assert!("".parse::<Name>().is_err());
+}
+
+#[test]
+fn test_type_name() {
+  assert!(MessageTypeName::try_new("std_msgs", "String").is_ok());
+  assert!(MessageTypeName::try_new("std_msgs", "").is_err()); // i.e. "std_msgs/"
+  assert!(MessageTypeName::try_new("", "String").is_err());
+  assert!(MessageTypeName::try_new("std_msgs/", "String").is_err());
+  assert!(MessageTypeName::try_new("Std_msgs", "String").is_err());
+  assert!(MessageTypeName::try_new("std__msgs", "String").is_err());
+  assert!(MessageTypeName::try_new("std_msgs_", "String").is_err());
+  assert!(MessageTypeName::try_new("2d_msgs", "Point").is_err());
+  assert!(MessageTypeName::try_new("geometry_msgs", "Point32").is_ok());
+  assert!(MessageTypeName::try_new("std_msgs", "string").is_err());
+  assert!(MessageTypeName::try_new("std_msgs", "String_").is_err());
+  assert!(ServiceTypeName::try_new("example_interfaces", "AddTwoInts").is_ok());
+  assert!(ServiceTypeName::try_new("example_interfaces", "add_two_ints").is_err());
+  assert!(ActionTypeName::try_new("turtlesim", "RotateAbsolute").is_ok());
+  assert!(ActionTypeName::try_new("turtlesim/", "RotateAbsolute").is_err());
+}
@@ -4,44 +4,93 @@ use std::{
   //pin::pin,
 };
 
-use futures::{pin_mut, FutureExt, StreamExt};
+use futures::{pin_mut, Future, FutureExt, StreamExt};
 use async_channel::Receiver;
 #[allow(unused_imports)]
 use log::{debug, error, info, trace, warn};
 use serde::{de::DeserializeOwned, Serialize};
-use rustdds::{dds::CreateResult, *};
+use rustdds::{
+  dds::{CreateError, CreateResult},
+  *,
+};
 
 use crate::{
   action::*,
+  builtin_interfaces,
+  clock::Clock,
   context::Context,
   entities_info::{NodeEntitiesInfo, ParticipantEntitiesInfo},
   gid::Gid,
+  graph::{action_names_and_types, ros_topic_name, EndpointKind, GraphEvent, GraphTracker},
   log as ros_log,
   log::Log,
   names::*,
+  parameter_server::{NodeParameters, ParameterServer},
   parameters::*,
-  pubsub::{Publisher, Subscription},
+  pubsub::{KeyedPublisher, KeyedSubscription, Publisher, Subscription},
+  qos,
+  ros_args::RosArgs,
+  runtime,
   service::{Client, Server, Service, ServiceMapping},
 };
 
+/// Run the future made by `f` to completion while spinning `node`, blocking
+/// the current thread.
+///
+/// This saves spawning the [`Spinner`] separately, e.g.
+/// ```
+/// # use ros2_client::*;
+/// # let context = Context::new().unwrap();
+/// # let mut node = context
+/// #   .new_node(NodeName::new("/example", "talker").unwrap(), NodeOptions::new())
+/// #   .unwrap();
+/// # let topic = node
+/// #   .create_topic(
+/// #     &Name::new("/example", "topic").unwrap(),
+/// #     MessageTypeName::new("std_msgs", "String"),
+/// #     &DEFAULT_PUBLISHER_QOS,
+/// #   )
+/// #   .unwrap();
+/// let publisher = node.create_publisher::<String>(&topic, None).unwrap();
+/// ros2_client::spin(&mut node, |node| async move {
+///   if publisher.subscription_count() == 0 {
+///     println!("No subscribers yet for {}", node.fully_qualified_name());
+///   }
+///   publisher.async_publish("Hello".to_string()).await
+/// })
+/// .unwrap()
+/// .unwrap();
+/// ```
+///
+/// The future would typically wait for something that the Spinner tracks,
+/// e.g. [`Publisher::wait_for_subscribers`](crate::Publisher::wait_for_subscribers).
+///
+/// Several tasks can be run e.g. with `futures::future::join_all`. This is
+/// the blocking version of [`Node::spin_until`]. With feature `rt-tokio` it
+/// must not be called from within a Tokio runtime.
+pub fn spin<'a, F, Fut>(node: &'a mut Node, f: F) -> CreateResult<Fut::Output>
+where
+  F: FnOnce(&'a Node) -> Fut,
+  Fut: Future,
+{
+  runtime::block_on(node.spin_until(f))
+}
+
 /// Configuration of [Node]
 /// This is a builder-like struct.
 #[must_use]
 pub struct NodeOptions {
-  #[allow(dead_code)]
   cli_args: Vec<String>,
-  #[allow(dead_code)]
   use_global_arguments: bool, // process-wide command line args
-  enable_rosout: bool, // use rosout topic for logging?
+  enable_rosout: bool,        // use rosout topic for logging?
   enable_rosout_reading: bool,
-  #[allow(dead_code)]
+  rosout_min_level: ros_log::LogLevel,
   start_parameter_services: bool,
-  #[allow(dead_code)]
   parameter_overrides: Vec<Parameter>,
-  #[allow(dead_code)]
   allow_undeclared_parameters: bool,
-  #[allow(dead_code)]
   automatically_declare_parameters_from_overrides: bool,
+  default_publisher_qos: Option<QosPolicies>,
+  default_subscription_qos: Option<QosPolicies>,
   // The NodeOptions struct does not contain
   // node_name, context, or namespace, because
   // they ae always needed and have no reasonable default.
@@ -57,12 +106,40 @@ impl NodeOptions {
       use_global_arguments: true,
       enable_rosout: true,
       enable_rosout_reading: false,
+      rosout_min_level: ros_log::LogLevel::Debug,
       start_parameter_services: true,
       parameter_overrides: Vec::new(),
       allow_undeclared_parameters: false,
       automatically_declare_parameters_from_overrides: false,
+      default_publisher_qos: None,
+      default_subscription_qos: None,
     }
   }
+  /// Give command line arguments to the Node. Only the ROS arguments, i.e.
+  /// those following `--ros-args`, are used. Currently only name remapping
+  /// (`-r` or `--remap`) is supported:
+  ///
+  /// * `__node:=new_name` changes the Node base name
+  /// * `__ns:=/new_namespace` changes the Node namespace
+  /// * `from:=to` remaps topic name `from` to `to`
+  ///
+  /// A rule can be limited to a single Node by prefixing it with the
+  /// original Node name, e.g. `talker:chatter:=/my_chatter`.
+  ///
+  /// These take precedence over global arguments.
+  pub fn cli_args(self, cli_args: Vec<String>) -> NodeOptions {
+    NodeOptions { cli_args, ..self }
+  }
+
+  /// Whether to also use the process command line arguments
+  /// (`std::env::args()`) as in [`NodeOptions::cli_args`]. Default is `true`.
+  pub fn use_global_arguments(self, use_global_arguments: bool) -> NodeOptions {
+    NodeOptions {
+      use_global_arguments,
+      ..self
+    }
+  }
+
   pub fn enable_rosout(self, enable_rosout: bool) -> NodeOptions {
     NodeOptions {
       enable_rosout,
@@ -76,6 +153,73 @@ impl NodeOptions {
       ..self
     }
   }
+
+  /// Messages below this level are not published to rosout. The default is
+  /// [`Debug`](ros_log::LogLevel::Debug), i.e. everything is published.
+  pub fn rosout_min_level(self, rosout_min_level: ros_log::LogLevel) -> NodeOptions {
+    NodeOptions {
+      rosout_min_level,
+      ..self
+    }
+  }
+
+  /// Enable or disable [`Node::parameter_server`].
+  pub fn start_parameter_services(self, start_parameter_services: bool) -> NodeOptions {
+    NodeOptions {
+      start_parameter_services,
+      ..self
+    }
+  }
+
+  /// Allow setting parameters that have not been declared with
+  /// [`Node::declare_parameter`]. Applies also to remote set requests.
+  pub fn allow_undeclared_parameters(self, allow_undeclared_parameters: bool) -> NodeOptions {
+    NodeOptions {
+      allow_undeclared_parameters,
+      ..self
+    }
+  }
+
+  /// Give parameter values that override the values given in
+  /// [`Node::declare_parameter`].
+  pub fn parameter_overrides(self, parameter_overrides: Vec<Parameter>) -> NodeOptions {
+    NodeOptions {
+      parameter_overrides,
+      ..self
+    }
+  }
+
+  /// If set, parameters given in [`NodeOptions::parameter_overrides`] are
+  /// declared already when the Node is created. Otherwise, overrides take
+  /// effect only when a parameter with the same name is declared.
+  pub fn automatically_declare_parameters_from_overrides(self, auto_declare: bool) -> NodeOptions {
+    NodeOptions {
+      automatically_declare_parameters_from_overrides: auto_declare,
+      ..self
+    }
+  }
+
+  /// Set the QoS policies used by Publishers created by this Node, when no QoS
+  /// is given to [`Node::create_publisher`].
+  ///
+  /// See [`Node::create_publisher`] for precedence rules.
+  pub fn default_publisher_qos(self, qos: QosPolicies) -> NodeOptions {
+    NodeOptions {
+      default_publisher_qos: Some(qos),
+      ..self
+    }
+  }
+
+  /// Set the QoS policies used by Subscriptions created by this Node, when no
+  /// QoS is given to [`Node::create_subscription`].
+  ///
+  /// See [`Node::create_subscription`] for precedence rules.
+  pub fn default_subscription_qos(self, qos: QosPolicies) -> NodeOptions {
+    NodeOptions {
+      default_subscription_qos: Some(qos),
+      ..self
+    }
+  }
 }
 
 impl Default for NodeOptions {
@@ -103,13 +247,18 @@ pub enum NodeEvent {
 pub struct Spinner {
   ros_context: Context,
   stop_spin_receiver: async_channel::Receiver<()>,
+  // Never used for sending. Dropping it tells Node::shutdown that the Spinner
+  // has stopped.
+  _stopped_sender: async_channel::Sender<()>,
 
-  readers_to_remote_writers: Arc<Mutex<BTreeMap<GUID, BTreeSet<GUID>>>>,
-  writers_to_remote_readers: Arc<Mutex<BTreeMap<GUID, BTreeSet<GUID>>>>,
+  readers_to_remote_writers: MatchedEndpoints,
+  writers_to_remote_readers: MatchedEndpoints,
   // Keep track of ros_discovery_info
   external_nodes: Arc<Mutex<BTreeMap<Gid, Vec<NodeEntitiesInfo>>>>,
 
   status_event_senders: Arc<Mutex<Vec<async_channel::Sender<NodeEvent>>>>,
+  graph_event_senders: Arc<Mutex<Vec<async_channel::Sender<GraphEvent>>>>,
+  graph: Arc<Mutex<GraphTracker>>,
 }
 
 impl Spinner {
@@ -137,7 +286,13 @@ impl Spinner {
             Ok((part_update, _msg_info)) => {
               // insert to Node-local ros_discovery_info bookkeeping
               let mut info_map = self.external_nodes.lock().unwrap();
-              info_map.insert( part_update.gid, part_update.node_entities_info_seq.clone());
+              let old_nodes = info_map
+                .insert( part_update.gid, part_update.node_entities_info_seq.clone())
+                .unwrap_or_default();
+              drop(info_map);
+              for event in GraphTracker::nodes_updated(&old_nodes, &part_update.node_entities_info_seq) {
+                self.send_graph_event(&event);
+              }
               // also notify any status listeneners
               self.send_status_event( &NodeEvent::ROS(part_update) );
             }
@@ -151,34 +306,54 @@ impl Spinner {
           //println!("{:?}", dp_status_event );
 
           // update remote reader/writer databases
-          match dp_status_event {
+          let mut graph_events = Vec::new();
+          match &dp_status_event {
             DomainParticipantStatusEvent::RemoteReaderMatched { local_writer, remote_reader } => {
-              self.writers_to_remote_readers.lock().unwrap()
-                .entry(local_writer)
-                .and_modify(|s| {s.insert(remote_reader);} )
-                .or_insert(BTreeSet::from([remote_reader]));
+              add_matched_endpoint(&mut self.writers_to_remote_readers.lock().unwrap(),
+                *local_writer, *remote_reader);
             }
             DomainParticipantStatusEvent::RemoteWriterMatched { local_reader, remote_writer } => {
-              self.readers_to_remote_writers.lock().unwrap()
-                .entry(local_reader)
-                .and_modify(|s| {s.insert(remote_writer);} )
-                .or_insert(BTreeSet::from([remote_writer]));
+              add_matched_endpoint(&mut self.readers_to_remote_writers.lock().unwrap(),
+                *local_reader, *remote_writer);
+            }
+            DomainParticipantStatusEvent::ReaderDetected { reader } => {
+              graph_events.extend(self.graph.lock().unwrap().endpoint_discovered(
+                EndpointKind::Subscription, reader.guid, &reader.topic_name, &reader.type_name,
+                &self.external_nodes.lock().unwrap()));
+            }
+            DomainParticipantStatusEvent::WriterDetected { writer } => {
+              graph_events.extend(self.graph.lock().unwrap().endpoint_discovered(
+                EndpointKind::Publisher, writer.guid, &writer.topic_name, &writer.type_name,
+                &self.external_nodes.lock().unwrap()));
             }
             DomainParticipantStatusEvent::ReaderLost {guid, ..} => {
-              for ( _local, readers)
-              in self.writers_to_remote_readers.lock().unwrap().iter_mut() {
-                readers.remove(&guid);
-              }
+              remove_matched_endpoint(&mut self.writers_to_remote_readers.lock().unwrap(), *guid);
+              graph_events.extend(self.graph.lock().unwrap()
+                .endpoint_lost(*guid, &self.external_nodes.lock().unwrap()));
             }
             DomainParticipantStatusEvent::WriterLost {guid, ..} => {
-              for ( _local, writers)
-              in self.readers_to_remote_writers.lock().unwrap().iter_mut() {
-                writers.remove(&guid);
+              remove_matched_endpoint(&mut self.readers_to_remote_writers.lock().unwrap(), *guid);
+              graph_events.extend(self.graph.lock().unwrap()
+                .endpoint_lost(*guid, &self.external_nodes.lock().unwrap()));
+            }
+            DomainParticipantStatusEvent::ParticipantLost { id, .. } => {
+              // The lost participant cannot tell us that its Nodes are gone.
+              let mut info_map = self.external_nodes.lock().unwrap();
+              let lost: Vec<Gid> = info_map.keys()
+                .filter(|gid| GUID::from(**gid).prefix == *id)
+                .copied()
+                .collect();
+              for gid in lost {
+                let old_nodes = info_map.remove(&gid).unwrap_or_default();
+                graph_events.extend(GraphTracker::nodes_updated(&old_nodes, &[]));
               }
             }
 
             _ => {}
           }
+          for event in graph_events {
+            self.send_graph_event(&event);
+          }
 
           // also notify any status listeneners
           self.send_status_event( &NodeEvent::DDS(dp_status_event) );
@@ -191,24 +366,74 @@ impl Spinner {
   } // fn
 
   fn send_status_event(&self, event: &NodeEvent) {
-    let mut closed = Vec::new();
-    let mut sender_array = self.status_event_senders.lock().unwrap();
-    for (i, sender) in sender_array.iter().enumerate() {
-      match sender.try_send(event.clone()) {
-        Ok(()) => {}
-        Err(async_channel::TrySendError::Closed(_)) => {
-          closed.push(i) // mark for deletion
-        }
-        Err(_) => {}
+    send_to_all(&self.status_event_senders, event)
+  }
+
+  fn send_graph_event(&self, event: &GraphEvent) {
+    send_to_all(&self.graph_event_senders, event)
+  }
+} // impl Spinner
+
+// Receivers that are full miss the event.
+fn send_to_all<T: Clone>(senders: &Mutex<Vec<async_channel::Sender<T>>>, event: &T) {
+  let mut closed = Vec::new();
+  let mut sender_array = senders.lock().unwrap();
+  for (i, sender) in sender_array.iter().enumerate() {
+    match sender.try_send(event.clone()) {
+      Ok(()) => {}
+      Err(async_channel::TrySendError::Closed(_)) => {
+        closed.push(i) // mark for deletion
       }
+      Err(_) => {}
     }
+  }
 
-    // remove senders that reported they were closed
-    for c in closed.iter().rev() {
-      sender_array.swap_remove(*c);
-    }
+  // remove senders that reported they were closed
+  for c in closed.iter().rev() {
+    sender_array.swap_remove(*c);
   }
-} // impl Spinner
+}
+
+// Other endpoints on the Topic presumably use the Topic QoS. If our endpoint
+// cannot match them, the user gets no data, and nothing says why. Say it.
+fn warn_if_incompatible_qos(topic: &Topic, qos: Option<&QosPolicies>, kind: EndpointKind) {
+  let topic_qos = topic.qos();
+  let endpoint_qos = match qos {
+    Some(qos) => topic_qos.modify_by(qos),
+    None => return, // Topic QoS as such
+  };
+  let problems = match kind {
+    EndpointKind::Subscription => qos::incompatibilities(&topic_qos, &endpoint_qos),
+    EndpointKind::Publisher => qos::incompatibilities(&endpoint_qos, &topic_qos),
+  };
+  for problem in problems {
+    warn!(
+      "{kind:?} QoS is incompatible with QoS of Topic {}: {problem}",
+      topic.name()
+    );
+  }
+}
+
+// Bookkeeping of which remote endpoints are matched to our local endpoints.
+// Map keys are local endpoints, values are the matched remote endpoints.
+pub(crate) type MatchedEndpoints = Arc<Mutex<BTreeMap<GUID, BTreeSet<GUID>>>>;
+
+fn add_matched_endpoint(matched: &mut BTreeMap<GUID, BTreeSet<GUID>>, local: GUID, remote: GUID) {
+  matched.entry(local).or_default().insert(remote);
+}
+
+fn remove_matched_endpoint(matched: &mut BTreeMap<GUID, BTreeSet<GUID>>, remote: GUID) {
+  for remotes in matched.values_mut() {
+    remotes.remove(&remote);
+  }
+}
+
+pub(crate) fn matched_endpoint_count(
+  matched: &BTreeMap<GUID, BTreeSet<GUID>>,
+  local: GUID,
+) -> usize {
+  matched.get(&local).map(BTreeSet::len).unwrap_or(0)
+}
 
 // ----------------------------------------------------------------------------------------------------
 // ----------------------------------------------------------------------------------------------------
@@ -230,24 +455,41 @@ pub struct Node {
   // These indicate what has been created locally.
   readers: BTreeSet<Gid>,
   writers: BTreeSet<Gid>,
+  // (DDS topic name, DDS type name) of the above
+  local_topics: BTreeSet<(String, String)>,
+  // DDS topic names of the above, for counting
 
   // Keep track of who is matched via DDS Discovery
   // Map keys are lists of local Subscriptions and Publishers.
   // Map values are lists of matched Publishers / Subscriptions.
-  readers_to_remote_writers: Arc<Mutex<BTreeMap<GUID, BTreeSet<GUID>>>>,
-  writers_to_remote_readers: Arc<Mutex<BTreeMap<GUID, BTreeSet<GUID>>>>,
+  readers_to_remote_writers: MatchedEndpoints,
+  writers_to_remote_readers: MatchedEndpoints,
 
   // Keep track of ros_discovery_info
   external_nodes: Arc<Mutex<BTreeMap<Gid, Vec<NodeEntitiesInfo>>>>,
   stop_spin_sender: Option<async_channel::Sender<()>>,
+  spinner_stopped_receiver: Option<async_channel::Receiver<()>>,
+  stop_parameter_server_sender: Option<async_channel::Sender<()>>,
+  is_shut_down: bool, // by Node::shutdown, so that Drop has nothing to do
 
   // Channels to report discovery events
   status_event_senders: Arc<Mutex<Vec<async_channel::Sender<NodeEvent>>>>,
+  graph_event_senders: Arc<Mutex<Vec<async_channel::Sender<GraphEvent>>>>,
+  // Remote ROS Publishers and Subscriptions, updated by Spinner
+  graph: Arc<Mutex<GraphTracker>>,
 
   // builtin writers and readers
   rosout_writer: Option<Publisher<Log>>,
   rosout_reader: Option<Subscription<Log>>,
-  parameter_events_writer: Publisher<raw::ParameterEvent>,
+
+  // Parameter values and parameter_events writer
+  parameters: Arc<NodeParameters>,
+
+  // Name remapping rules from command line arguments
+  ros_args: RosArgs,
+
+  // System or simulation time
+  clock: Clock,
 }
 
 impl Node {
@@ -256,10 +498,24 @@ impl Node {
     options: NodeOptions,
     ros_context: Context,
   ) -> CreateResult<Node> {
+    // Node-specific arguments take precedence over process-wide ones.
+    let mut ros_args =
+      RosArgs::parse(&options.cli_args).map_err(|reason| CreateError::BadParameter { reason })?;
+    if options.use_global_arguments {
+      let global_args: Vec<String> = std::env::args().collect();
+      ros_args.extend(
+        RosArgs::parse(&global_args).map_err(|reason| CreateError::BadParameter { reason })?,
+      );
+    }
+    let ros_args = ros_args.for_node(node_name.base_name());
+    let node_name = ros_args
+      .remap_node_name(node_name)
+      .map_err(|reason| CreateError::BadParameter { reason })?;
+
     let paramtopic = ros_context.get_parameter_events_topic();
     let rosout_topic = ros_context.get_rosout_topic();
 
-    let rosout_writer = if options.enable_rosout {
+    let mut rosout_writer = if options.enable_rosout {
       Some(
         // topic already has QoS defined
         ros_context.create_publisher(&rosout_topic, None)?,
@@ -267,29 +523,64 @@ impl Node {
     } else {
       None
     };
-    let rosout_reader = if options.enable_rosout_reading {
+    let mut rosout_reader = if options.enable_rosout_reading {
       Some(ros_context.create_subscription(&rosout_topic, None)?)
     } else {
       None
     };
 
     let parameter_events_writer = ros_context.create_publisher(&paramtopic, None)?;
+    let parameters = Arc::new(NodeParameters::new(
+      node_name.fully_qualified_name(),
+      options.allow_undeclared_parameters,
+      options.parameter_overrides.clone(),
+      parameter_events_writer,
+    ));
+    if options.automatically_declare_parameters_from_overrides {
+      parameters
+        .declare_overrides()
+        .unwrap_or_else(|e| error!("Cannot declare parameter overrides: {e}"));
+    }
+
+    // Matching of the rosout endpoints is tracked like that of any other
+    // endpoint of the Node.
+    let readers_to_remote_writers: MatchedEndpoints = Arc::new(Mutex::new(BTreeMap::new()));
+    let writers_to_remote_readers: MatchedEndpoints = Arc::new(Mutex::new(BTreeMap::new()));
+    if let Some(w) = rosout_writer.as_mut() {
+      w.set_matched_subscriptions(Arc::clone(&writers_to_remote_readers));
+    }
+    if let Some(r) = rosout_reader.as_mut() {
+      r.set_matched_publishers(Arc::clone(&readers_to_remote_writers));
+    }
 
-    Ok(Node {
+    let mut node = Node {
       node_name,
       options,
       ros_context,
       readers: BTreeSet::new(),
       writers: BTreeSet::new(),
-      readers_to_remote_writers: Arc::new(Mutex::new(BTreeMap::new())),
-      writers_to_remote_readers: Arc::new(Mutex::new(BTreeMap::new())),
+      local_topics: BTreeSet::new(),
+      readers_to_remote_writers,
+      writers_to_remote_readers,
       external_nodes: Arc::new(Mutex::new(BTreeMap::new())),
       stop_spin_sender: None,
+      spinner_stopped_receiver: None,
+      stop_parameter_server_sender: None,
+      is_shut_down: false,
       status_event_senders: Arc::new(Mutex::new(Vec::new())),
+      graph_event_senders: Arc::new(Mutex::new(Vec::new())),
+      graph: Arc::new(Mutex::new(GraphTracker::default())),
       rosout_writer,
       rosout_reader,
-      parameter_events_writer,
-    })
+      parameters,
+      ros_args,
+      clock: Clock::new(),
+    };
+    // Announce the Node in ROS Discovery, even if it has no Publishers or
+    // Subscriptions yet.
+    let node_info = node.generate_node_info();
+    node.ros_context.update_node(node_info);
+    Ok(node)
   }
 
   /// Create a Spinner object to execute Node backround tasks.
@@ -297,31 +588,193 @@ impl Node {
   /// An async task should then be created to run the `.spin()` function of
   /// `Spinner`.
   ///
-  /// E.g. `executor.spawn(node.spinner().spin())`
+  /// E.g. `executor.spawn(node.spinner()?.spin())`
   ///
   /// The `.spin()` task runs until `Node` is dropped.
-  pub fn spinner(&mut self) -> Spinner {
-    if self.stop_spin_sender.is_some() {
-      panic!("Attempted to crate a second spinner.");
+  ///
+  /// Fails if a Spinner already exists. A new Spinner can be created once the
+  /// previous one has been dropped, e.g. when its task has ended.
+  pub fn spinner(&mut self) -> CreateResult<Spinner> {
+    if let Some(stop_spin_sender) = &self.stop_spin_sender {
+      if stop_spin_sender.receiver_count() > 0 {
+        return Err(CreateError::BadParameter {
+          reason: "Attempted to create a second Spinner.".to_string(),
+        });
+      }
     }
     let (stop_spin_sender, stop_spin_receiver) = async_channel::bounded(1);
     self.stop_spin_sender = Some(stop_spin_sender);
+    let (stopped_sender, stopped_receiver) = async_channel::bounded(1);
+    self.spinner_stopped_receiver = Some(stopped_receiver);
 
-    Spinner {
+    Ok(Spinner {
       ros_context: self.ros_context.clone(),
       stop_spin_receiver,
+      _stopped_sender: stopped_sender,
       readers_to_remote_writers: Arc::clone(&self.readers_to_remote_writers),
       writers_to_remote_readers: Arc::clone(&self.writers_to_remote_readers),
       external_nodes: Arc::clone(&self.external_nodes),
       status_event_senders: Arc::clone(&self.status_event_senders),
+      graph_event_senders: Arc::clone(&self.graph_event_senders),
+      graph: Arc::clone(&self.graph),
+    })
+  }
+
+  /// Run the future made by `f` to completion, while spinning this Node.
+  ///
+  /// This creates a [`Spinner`] and runs its `.spin()` together with the
+  /// future, so that Discovery is processed while the future runs. When the
+  /// future completes, the Spinner is dropped, and its result is returned.
+  /// The future can use the Node via the reference given to `f`, e.g. to
+  /// wait for subscribers.
+  ///
+  /// Fails, if a Spinner already exists, or if spinning fails before the
+  /// future completes. See also [`spin`], which blocks.
+  pub async fn spin_until<'a, F, Fut>(&'a mut self, f: F) -> CreateResult<Fut::Output>
+  where
+    F: FnOnce(&'a Node) -> Fut,
+    Fut: Future,
+  {
+    let spinning = self.spinner()?.spin().fuse();
+    let node: &'a Node = self;
+    let user_future = f(node).fuse();
+    pin_mut!(spinning, user_future);
+    futures::select! {
+      output = user_future => Ok(output),
+      spin_result = spinning => {
+        // Spinning ended first, so we can no longer keep Discovery alive.
+        spin_result?;
+        Err(CreateError::Internal {
+          reason: "Spinner stopped before the future completed.".to_string(),
+        })
+      }
+    }
+  }
+
+  /// Shut down the Node, and wait until that is complete.
+  ///
+  /// This stops the [`Spinner`] and waits for its `.spin()` to exit, stops
+  /// the [`ParameterServer`], and removes the Node from ROS Discovery. Unlike
+  /// dropping the Node, this reports if something fails.
+  ///
+  /// If a Spinner exists, its `.spin()` must be running, or this does not
+  /// complete.
+  pub async fn shutdown(mut self) -> CreateResult<()> {
+    if let Some(stop_spin_sender) = self.stop_spin_sender.take() {
+      if stop_spin_sender.receiver_count() > 0 {
+        stop_spin_sender
+          .send(())
+          .await
+          .map_err(|e| CreateError::Internal {
+            reason: format!("Cannot notify spin task to stop: {e}"),
+          })?;
+        if let Some(spinner_stopped) = self.spinner_stopped_receiver.take() {
+          // Nothing is ever sent. This ends when the Spinner is dropped.
+          while spinner_stopped.recv().await.is_ok() {}
+        }
+      }
+    }
+    if let Some(stop_sender) = self.stop_parameter_server_sender.take() {
+      if stop_sender.receiver_count() > 0 {
+        stop_sender
+          .send(())
+          .await
+          .map_err(|e| CreateError::Internal {
+            reason: format!("Cannot notify parameter server task to stop: {e}"),
+          })?;
+      }
+    }
+    self
+      .ros_context
+      .remove_node(self.fully_qualified_name().as_str());
+    self.is_shut_down = true;
+    Ok(())
+  }
+
+  /// Create a ParameterServer object to serve the ROS 2 parameter Services of
+  /// this Node.
+  ///
+  /// An async task should then be created to run the `.serve()` function of
+  /// `ParameterServer`, similarly to [`Spinner`].
+  ///
+  /// E.g. `executor.spawn(node.parameter_server()?.serve())`
+  ///
+  /// Fails if parameter services are disabled in [`NodeOptions`], or if a
+  /// ParameterServer has already been created.
+  pub fn parameter_server(&mut self) -> CreateResult<ParameterServer> {
+    if !self.options.start_parameter_services {
+      return Err(CreateError::BadParameter {
+        reason: "Parameter services are disabled in NodeOptions.".to_string(),
+      });
     }
+    if self.stop_parameter_server_sender.is_some() {
+      return Err(CreateError::BadParameter {
+        reason: "Attempted to create a second ParameterServer.".to_string(),
+      });
+    }
+    let (stop_sender, stop_receiver) = async_channel::bounded(1);
+    let parameters = Arc::clone(&self.parameters);
+    let parameter_server = ParameterServer::new(self, parameters, stop_receiver)?;
+    self.stop_parameter_server_sender = Some(stop_sender);
+    Ok(parameter_server)
+  }
+
+  /// Declare a parameter with an initial value.
+  ///
+  /// If the parameter is overridden in [`NodeOptions::parameter_overrides`],
+  /// the override value is used instead.
+  ///
+  /// Fails if the parameter has already been declared.
+  pub fn declare_parameter(&self, name: &str, value: ParameterValue) -> Result<(), ParameterError> {
+    self.parameters.declare(name, value)
+  }
+
+  /// Declare a parameter with an initial value and a descriptor.
+  ///
+  /// Later changes to the value, by [`Node::set_parameter`] or via parameter
+  /// Services, are rejected if the parameter is read-only or if the value is
+  /// not within the range of the descriptor. The descriptor is reported by
+  /// the `describe_parameters` Service.
+  ///
+  /// Fails if the parameter has already been declared, or if the initial
+  /// value is not within the range.
+  pub fn declare_parameter_with_descriptor(
+    &self,
+    name: &str,
+    value: ParameterValue,
+    descriptor: ParameterDescriptor,
+  ) -> Result<(), ParameterError> {
+    self
+      .parameters
+      .declare_with_descriptor(name, value, descriptor)
+  }
+
+  /// Get the current value of a parameter, if it is set.
+  pub fn get_parameter(&self, name: &str) -> Option<ParameterValue> {
+    self.parameters.get(name)
+  }
+
+  /// Set the value of a parameter.
+  ///
+  /// The parameter must have been declared, unless
+  /// [`NodeOptions::allow_undeclared_parameters`] is set. Setting the value
+  /// [`ParameterValue::NotSet`] removes the parameter.
+  pub fn set_parameter(&self, name: &str, value: ParameterValue) -> Result<(), ParameterError> {
+    self
+      .parameters
+      .set(vec![Parameter {
+        name: name.to_string(),
+        value,
+      }])
+      .pop()
+      .unwrap_or(Ok(()))
   }
 
   // Generates ROS2 node info from added readers and writers.
   fn generate_node_info(&self) -> NodeEntitiesInfo {
     let mut node_info = NodeEntitiesInfo::new(self.node_name.clone());
 
-    node_info.add_writer(Gid::from(self.parameter_events_writer.guid()));
+    node_info.add_writer(Gid::from(self.parameters.events_writer_guid()));
     if let Some(row) = &self.rosout_writer {
       node_info.add_writer(Gid::from(row.guid()));
     }
@@ -347,6 +800,12 @@ impl Node {
     self.ros_context.update_node(self.generate_node_info());
   }
 
+  fn add_local_topic(&mut self, topic: &Topic) {
+    self
+      .local_topics
+      .insert((topic.name(), topic.get_type().name().to_string()));
+  }
+
   pub fn base_name(&self) -> &str {
     self.node_name.base_name()
   }
@@ -367,6 +826,42 @@ impl Node {
     self.ros_context.domain_id()
   }
 
+  /// Use simulation time from the `/clock` Topic instead of system time.
+  ///
+  /// This affects [`Node::now`], `rosout!` timestamps, and Action Servers
+  /// created by this Node, also those created earlier.
+  pub fn use_sim_time(&mut self, use_sim_time: bool) -> CreateResult<()> {
+    if use_sim_time == self.clock.is_sim_time() {
+      return Ok(());
+    }
+    let clock_subscription = if use_sim_time {
+      let clock_topic = self.create_topic(
+        &Name::new("/", "clock").unwrap(),
+        MessageTypeName::new("rosgraph_msgs", "Clock"),
+        &QosPolicyBuilder::new()
+          .reliability(policy::Reliability::BestEffort)
+          .history(policy::History::KeepLast { depth: 1 })
+          .durability(policy::Durability::Volatile)
+          .build(),
+      )?;
+      Some(self.create_subscription(&clock_topic, None)?)
+    } else {
+      None
+    };
+    self.clock.set_sim_time_source(clock_subscription);
+    Ok(())
+  }
+
+  /// Current time according to the [`Clock`] of this Node.
+  pub fn now(&self) -> builtin_interfaces::Time {
+    self.clock.now()
+  }
+
+  /// Get (a shared handle to) the [`Clock`] of this Node.
+  pub fn clock(&self) -> Clock {
+    self.clock.clone()
+  }
+
   /// Get an async Receiver for discovery events.
   ///
   /// There must be an async task executing `spin` to get any data.
@@ -380,6 +875,132 @@ impl Node {
     status_event_receiver
   }
 
+  /// Get an async Receiver for changes in the ROS 2 graph.
+  ///
+  /// This is a higher-level alternative to [`Self::status_receiver`]. Only
+  /// remote Publishers and Subscriptions are reported.
+  ///
+  /// There must be an async task executing `spin` to get any data.
+  pub fn graph_events(&self) -> Receiver<GraphEvent> {
+    // Larger than for status events, as there may be a burst of events when a
+    // new participant is discovered.
+    let (graph_event_sender, graph_event_receiver) = async_channel::bounded(64);
+    self
+      .graph_event_senders
+      .lock()
+      .unwrap()
+      .push(graph_event_sender);
+    graph_event_receiver
+  }
+
+  /// Get the names of all Nodes in the ROS graph, including this one.
+  ///
+  /// Nodes in other DomainParticipants (processes) are learned via ROS
+  /// Discovery, so there must be an async task executing `spin` to see them.
+  /// Otherwise the result contains only Nodes in this [`Context`].
+  pub fn get_node_names(&self) -> Vec<NodeName> {
+    let local_nodes = self.ros_context.participant_entities_info();
+    let external_nodes = self.external_nodes.lock().unwrap();
+
+    let mut names: Vec<NodeName> = Vec::new();
+    let all_nodes = local_nodes
+      .nodes()
+      .iter()
+      .chain(external_nodes.values().flatten());
+    for name in std::iter::once(self.node_name.clone()).chain(all_nodes.map(|n| n.node_name())) {
+      if !names.contains(&name) {
+        names.push(name);
+      }
+    }
+    names
+  }
+
+  /// Get the names of all ROS Topics in the graph, and their message types.
+  ///
+  /// Only ROS Topics are listed, i.e. Service request/response topics and
+  /// plain DDS Topics are not.
+  ///
+  /// Topics of other DomainParticipants are learned via DDS Discovery, so
+  /// there must be an async task executing `spin` to get a complete result.
+  pub fn get_topic_names_and_types(&self) -> BTreeMap<String, Vec<MessageTypeName>> {
+    let mut result: BTreeMap<String, Vec<MessageTypeName>> = BTreeMap::new();
+    for (dds_topic_name, dds_type_name) in self.all_dds_topics() {
+      let topic_name = match ros_topic_name(&dds_topic_name) {
+        Some(name) => name,
+        None => continue, // not a ROS Topic
+      };
+      let type_name = match MessageTypeName::from_dds_msg_type(&dds_type_name) {
+        Some(type_name) => type_name,
+        None => {
+          debug!("Topic {topic_name} has non-ROS type name {dds_type_name}");
+          continue;
+        }
+      };
+      let types = result.entry(topic_name).or_default();
+      if !types.contains(&type_name) {
+        types.push(type_name);
+      }
+    }
+    result
+  }
+
+  /// Get the names of all Actions in the ROS graph, and their types.
+  ///
+  /// This is similar to `ros2 action list`. Actions are recognized from the
+  /// names of the Topics and Services they consist of. An Action is listed
+  /// only when all of them (`send_goal`, `cancel_goal` and `get_result`
+  /// Services, `feedback` and `status` Topics) are known.
+  ///
+  /// Actions of other DomainParticipants are learned via DDS Discovery, so
+  /// there must be an async task executing `spin` to get a complete result.
+  pub fn get_action_names_and_types(&self) -> BTreeMap<String, Vec<ActionTypeName>> {
+    action_names_and_types(self.all_dds_topics())
+  }
+
+  /// Count the Publishers on a Topic, like `ros2 topic info`.
+  ///
+  /// Publishers of all Nodes in the same Context are counted, as long as they
+  /// exist, and so are remote Publishers. Remote Publishers are learned via DDS
+  /// Discovery, so there must be an async task executing `spin` to get a
+  /// complete result. `topic_name` is remapped and resolved like in
+  /// [`Self::create_topic`].
+  pub fn count_publishers(&self, topic_name: &Name) -> usize {
+    self.count_endpoints(EndpointKind::Publisher, topic_name)
+  }
+
+  /// Count the Subscriptions on a Topic. See [`Self::count_publishers`].
+  pub fn count_subscribers(&self, topic_name: &Name) -> usize {
+    self.count_endpoints(EndpointKind::Subscription, topic_name)
+  }
+
+  fn count_endpoints(&self, kind: EndpointKind, topic_name: &Name) -> usize {
+    // Same name resolution as in create_topic
+    let topic_name = self.ros_args.remap_topic(topic_name, &self.node_name);
+    let dds_topic_name = topic_name.to_dds_name("rt", &self.node_name, "");
+    let local = self
+      .ros_context
+      .count_local_endpoints(kind, &dds_topic_name);
+    let remote = ros_topic_name(&dds_topic_name).map_or(0, |ros_topic| {
+      self.graph.lock().unwrap().count(kind, &ros_topic)
+    });
+    local + remote
+  }
+
+  // DDS Topic names and types, both local and discovered.
+  fn all_dds_topics(&self) -> Vec<(String, String)> {
+    let discovered = self
+      .ros_context
+      .discovered_topics()
+      .into_iter()
+      .map(|dt| (dt.topic_name().clone(), dt.type_name().clone()));
+    self
+      .local_topics
+      .iter()
+      .cloned()
+      .chain(discovered)
+      .collect()
+  }
+
   // reader waits for at least one writer to be present
   pub(crate) async fn wait_for_writer(&self, reader: GUID) {
     // TODO: This may contain some synchrnoization hazard
@@ -411,56 +1032,33 @@ impl Node {
   }
 
   pub(crate) async fn wait_for_reader(&self, writer: GUID) {
+    self.wait_for_readers(writer, 1).await
+  }
+
+  // writer waits for at least `count` readers to be present
+  pub(crate) async fn wait_for_readers(&self, writer: GUID, count: usize) {
+    // Subscribe to events before checking the map, so that no match is missed
+    // in between.
     let status_receiver = self.status_receiver();
     pin_mut!(status_receiver);
 
-    let already_present = self
-      .writers_to_remote_readers
-      .lock()
-      .unwrap()
-      .get(&writer)
-      .map(|readers| !readers.is_empty()) // there is someone matched
-      .unwrap_or(false); // we do not even know who is asking
-
-    if !already_present {
-      loop {
-        if let NodeEvent::DDS(DomainParticipantStatusEvent::RemoteReaderMatched {
-          local_writer,
-          ..
-        }) = status_receiver.select_next_some().await
-        {
-          if local_writer == writer {
-            break; // we got a match
-          }
-        }
+    // Readers may have been matched already, and may also be lost while
+    // waiting, so just count again on every event.
+    while matched_endpoint_count(&self.writers_to_remote_readers.lock().unwrap(), writer) < count {
+      if status_receiver.next().await.is_none() {
+        break; // Node is going away
       }
     }
   }
 
-  pub(crate) fn get_publisher_count(&self, subscription_guid: GUID) -> usize {
-    self
-      .readers_to_remote_writers
-      .lock()
-      .unwrap()
-      .get(&subscription_guid)
-      .map(BTreeSet::len)
-      .unwrap_or_else(|| {
-        error!("get_publisher_count: Subscriber {subscription_guid:?} not known to node.");
-        0
-      })
+  // Is the local writer matched to at least one remote reader?
+  pub(crate) fn has_remote_reader(&self, writer: GUID) -> bool {
+    matched_endpoint_count(&self.writers_to_remote_readers.lock().unwrap(), writer) > 0
   }
 
-  pub(crate) fn get_subscription_count(&self, publisher_guid: GUID) -> usize {
-    self
-      .writers_to_remote_readers
-      .lock()
-      .unwrap()
-      .get(&publisher_guid)
-      .map(BTreeSet::len)
-      .unwrap_or_else(|| {
-        error!("get_subscription_count: Publisher {publisher_guid:?} not known to node.");
-        0
-      })
+  // Is the local reader matched to at least one remote writer?
+  pub(crate) fn has_remote_writer(&self, reader: GUID) -> bool {
+    matched_endpoint_count(&self.readers_to_remote_writers.lock().unwrap(), reader) > 0
   }
 
   /// Borrow the Subscription to our ROSOut Reader.
@@ -470,6 +1068,41 @@ impl Node {
     self.rosout_reader.as_ref()
   }
 
+  /// Re-emit messages received from rosout via the `log` crate.
+  ///
+  /// This makes logging of the whole ROS graph visible in the logger of this
+  /// application. Levels are mapped as in [`Log::log_level`], and the log
+  /// target is the name of the logging Node.
+  ///
+  /// Requires [`NodeOptions::read_rosout`]. The returned future runs forever,
+  /// so spawn it as a task, like the [`Spinner`].
+  pub async fn rosout_to_log(&self) {
+    match &self.rosout_reader {
+      None => warn!("rosout_to_log: Rosout reading is not enabled in NodeOptions."),
+      Some(reader) => {
+        let log_stream = reader.async_stream();
+        pin_mut!(log_stream);
+        while let Some(result) = log_stream.next().await {
+          match result {
+            Ok((entry, _msg_info)) => entry.to_log(),
+            Err(e) => debug!("rosout_to_log: Read error {e:?}"),
+          }
+        }
+      }
+    }
+  }
+
+  /// Create a Subscription to the `/parameter_events` Topic, where Nodes
+  /// announce changes to their parameters.
+  ///
+  /// Use [`Subscription::parameter_event_stream`] to receive decoded events.
+  pub fn create_parameter_event_subscription(
+    &mut self,
+  ) -> CreateResult<Subscription<raw::ParameterEvent>> {
+    let topic = self.ros_context.get_parameter_events_topic();
+    self.create_subscription(&topic, None)
+  }
+
   #[allow(clippy::too_many_arguments)]
   pub fn rosout_raw(
     &self,
@@ -483,6 +1116,7 @@ impl Node {
   ) {
     match &self.rosout_writer {
       None => debug!("Rosout not enabled. msg: {log_msg}"),
+      Some(_) if !rosout_level_passes(level, self.options.rosout_min_level) => {}
       Some(writer) => {
         writer
           .publish(ros_log::Log {
@@ -534,6 +1168,7 @@ impl Node {
     qos: &QosPolicies,
   ) -> CreateResult<Topic> {
     //let dds_name = Self::check_name_and_add_prefix("rt/", topic_name)?;
+    let topic_name = self.ros_args.remap_topic(topic_name, &self.node_name);
     let dds_name = topic_name.to_dds_name("rt", &self.node_name, "");
     info!("Creating topic, DDS name: {}", dds_name);
     let topic = self.ros_context.domain_participant().create_topic(
@@ -542,11 +1177,37 @@ impl Node {
       qos,
       TopicKind::NoKey,
     )?;
-    // ROS2 does not use WithKey topics, so always NoKey
+    // ROS2 does not use WithKey topics, so NoKey here.
+    // See create_keyed_topic for the exception.
     info!("Created topic");
     Ok(topic)
   }
 
+  /// Creates a keyed (DDS `WithKey`) Topic.
+  ///
+  /// Normal ROS 2 Topics are not keyed, but keyed Topics are needed for
+  /// interoperation with DDS applications that rely on instances, e.g.
+  /// instance disposal. Use [`Self::create_keyed_publisher`] and
+  /// [`Self::create_keyed_subscription`] with these Topics.
+  ///
+  /// Naming is the same as in [`Self::create_topic`].
+  pub fn create_keyed_topic(
+    &self,
+    topic_name: &Name,
+    type_name: MessageTypeName,
+    qos: &QosPolicies,
+  ) -> CreateResult<Topic> {
+    let topic_name = self.ros_args.remap_topic(topic_name, &self.node_name);
+    let dds_name = topic_name.to_dds_name("rt", &self.node_name, "");
+    info!("Creating keyed topic, DDS name: {}", dds_name);
+    self.ros_context.domain_participant().create_topic(
+      dds_name,
+      type_name.dds_msg_type(),
+      qos,
+      TopicKind::WithKey,
+    )
+  }
+
   /// Creates ROS2 Subscriber
   ///
   /// # Arguments
@@ -554,13 +1215,21 @@ impl Node {
   /// * `topic` - Reference to topic created with `create_ros_topic`.
   /// * `qos` - Should take [QOS](../dds/qos/struct.QosPolicies.html) and use if
   ///   it's compatible with topics QOS. `None` indicates the use of Topics QOS.
+  ///
+  /// QoS policies are resolved in the following order of precedence:
+  /// explicit `qos` argument > Topic QoS > Node default
+  /// ([`NodeOptions::default_subscription_qos`]) > system default.
   pub fn create_subscription<D: DeserializeOwned + 'static>(
     &mut self,
     topic: &Topic,
     qos: Option<QosPolicies>,
   ) -> CreateResult<Subscription<D>> {
-    let sub = self.ros_context.create_subscription(topic, qos)?;
+    let qos = Self::resolve_qos(qos, self.options.default_subscription_qos.as_ref(), topic);
+    warn_if_incompatible_qos(topic, qos.as_ref(), EndpointKind::Subscription);
+    let mut sub = self.ros_context.create_subscription(topic, qos)?;
+    sub.set_matched_publishers(Arc::clone(&self.readers_to_remote_writers));
     self.add_reader(sub.guid().into());
+    self.add_local_topic(topic);
     Ok(sub)
   }
 
@@ -572,16 +1241,79 @@ impl Node {
   /// * `qos` - Should take [QOS](../dds/qos/struct.QosPolicies.html) and use it
   ///   if it's compatible with topics QOS. `None` indicates the use of Topics
   ///   QOS.
+  ///
+  /// QoS policies are resolved in the following order of precedence:
+  /// explicit `qos` argument > Topic QoS > Node default
+  /// ([`NodeOptions::default_publisher_qos`]) > system default.
   pub fn create_publisher<D: Serialize>(
     &mut self,
     topic: &Topic,
     qos: Option<QosPolicies>,
   ) -> CreateResult<Publisher<D>> {
-    let p = self.ros_context.create_publisher(topic, qos)?;
+    let qos = Self::resolve_qos(qos, self.options.default_publisher_qos.as_ref(), topic);
+    warn_if_incompatible_qos(topic, qos.as_ref(), EndpointKind::Publisher);
+    let mut p = self.ros_context.create_publisher(topic, qos)?;
+    p.set_matched_subscriptions(Arc::clone(&self.writers_to_remote_readers));
+    self.add_writer(p.guid().into());
+    self.add_local_topic(topic);
+    Ok(p)
+  }
+
+  /// Creates a Publisher on a keyed Topic, see [`Self::create_keyed_topic`].
+  ///
+  /// QoS policies are resolved as in [`Self::create_publisher`].
+  pub fn create_keyed_publisher<D>(
+    &mut self,
+    topic: &Topic,
+    qos: Option<QosPolicies>,
+  ) -> CreateResult<KeyedPublisher<D>>
+  where
+    D: Keyed + Serialize,
+    D::K: Key,
+  {
+    let qos = Self::resolve_qos(qos, self.options.default_publisher_qos.as_ref(), topic);
+    warn_if_incompatible_qos(topic, qos.as_ref(), EndpointKind::Publisher);
+    let mut p = self.ros_context.create_keyed_publisher(topic, qos)?;
+    p.set_matched_subscriptions(Arc::clone(&self.writers_to_remote_readers));
     self.add_writer(p.guid().into());
+    self.add_local_topic(topic);
     Ok(p)
   }
 
+  /// Creates a Subscription on a keyed Topic, see [`Self::create_keyed_topic`].
+  ///
+  /// QoS policies are resolved as in [`Self::create_subscription`].
+  pub fn create_keyed_subscription<D>(
+    &mut self,
+    topic: &Topic,
+    qos: Option<QosPolicies>,
+  ) -> CreateResult<KeyedSubscription<D>>
+  where
+    D: 'static + Keyed + DeserializeOwned,
+    D::K: Key,
+  {
+    let qos = Self::resolve_qos(qos, self.options.default_subscription_qos.as_ref(), topic);
+    warn_if_incompatible_qos(topic, qos.as_ref(), EndpointKind::Subscription);
+    let mut sub = self.ros_context.create_keyed_subscription(topic, qos)?;
+    sub.set_matched_publishers(Arc::clone(&self.readers_to_remote_writers));
+    self.add_reader(sub.guid().into());
+    self.add_local_topic(topic);
+    Ok(sub)
+  }
+
+  // Explicit QoS is passed through as-is, because DDS will apply it on top of
+  // Topic QoS. If there is no explicit QoS, but Node has a default, then
+  // Topic QoS is layered on top of the Node default, so that policies defined
+  // in the Topic win. If neither is given, DDS uses Topic QoS over system
+  // defaults.
+  fn resolve_qos(
+    explicit_qos: Option<QosPolicies>,
+    node_default_qos: Option<&QosPolicies>,
+    topic: &Topic,
+  ) -> Option<QosPolicies> {
+    explicit_qos.or_else(|| node_default_qos.map(|default| default.modify_by(&topic.qos())))
+  }
+
   pub(crate) fn create_simpledatareader<D, DA>(
     &mut self,
     topic: &Topic,
@@ -644,6 +1376,9 @@ impl Node {
       TopicKind::NoKey,
     )?;
 
+    self.add_local_topic(&rq_topic);
+    self.add_local_topic(&rs_topic);
+
     let c = Client::<S>::new(
       service_mapping,
       self,
@@ -695,6 +1430,9 @@ impl Node {
       TopicKind::NoKey,
     )?;
 
+    self.add_local_topic(&rq_topic);
+    self.add_local_topic(&rs_topic);
+
     let s = Server::<S>::new(
       service_mapping,
       self,
@@ -781,6 +1519,7 @@ impl Node {
       my_feedback_subscription,
       my_status_subscription,
       my_action_name: action_name.clone(),
+      rejected_goals: Mutex::new(BTreeSet::new()),
     })
   }
 
@@ -856,16 +1595,29 @@ impl Node {
       my_feedback_publisher,
       my_status_publisher,
       my_action_name: action_name.clone(),
+      my_clock: self.clock(),
+      my_matched_writers: Arc::clone(&self.readers_to_remote_writers),
     })
   }
 } // impl Node
 
 impl Drop for Node {
   fn drop(&mut self) {
+    if self.is_shut_down {
+      return;
+    }
     if let Some(ref stop_spin_sender) = self.stop_spin_sender {
-      stop_spin_sender
+      if stop_spin_sender.receiver_count() > 0 {
+        // Spinner still exists
+        stop_spin_sender
+          .try_send(())
+          .unwrap_or_else(|e| error!("Cannot notify spin task to stop: {e:?}"));
+      }
+    }
+    if let Some(ref stop_sender) = self.stop_parameter_server_sender {
+      stop_sender
         .try_send(())
-        .unwrap_or_else(|e| error!("Cannot notify spin task to stop: {e:?}"));
+        .unwrap_or_else(|e| error!("Cannot notify parameter server task to stop: {e:?}"));
     }
 
     self
@@ -898,13 +1650,481 @@ macro_rules! rosout {
 
     ($node:expr, $lvl:expr, $($arg:tt)+) => (
         $node.rosout_raw(
-            $crate::ros2::Timestamp::now(),
+            $node.now().into(),
             $lvl,
             $node.base_name(),
             &std::format!($($arg)+), // msg
             std::file!(),
-            "<unknown_func>", // is there a macro to get current function name? (Which may be undefined)
+            $crate::function_name!(),
             std::line!(),
         );
     );
 }
+
+// The derived Ord of LogLevel follows declaration order, which puts Fatal
+// first. Compare the numeric values of the #[repr(u8)] enum instead: they grow
+// with severity.
+fn rosout_level_passes(level: ros_log::LogLevel, min_level: ros_log::LogLevel) -> bool {
+  level as u8 >= min_level as u8
+}
+
+/// Path of the enclosing function, e.g. `my_crate::module::my_function`.
+///
+/// Used by [`rosout!`]. There is no such macro in `std`, so we get the type name
+/// of a nested function and strip the nested part off.
+#[doc(hidden)]
+#[macro_export]
+macro_rules! function_name {
+  () => {{
+    fn f() {}
+    fn type_name_of<T>(_: T) -> &'static str {
+      std::any::type_name::<T>()
+    }
+    let name = type_name_of(f);
+    // Within closures (and async blocks), the path ends with "{{closure}}"
+    name
+      .strip_suffix("::f")
+      .unwrap_or(name)
+      .trim_end_matches("::{{closure}}")
+  }};
+}
+
+// -------------------------------------------------------------------------------------
+
+#[test]
+fn nodes_see_each_other_and_their_topics() {
+  let context = Context::new().unwrap();
+  let talker_name = NodeName::new("/graph_test", "talker").unwrap();
+  let listener_name = NodeName::new("/graph_test", "listener").unwrap();
+  let mut talker = context
+    .new_node(talker_name.clone(), NodeOptions::new())
+    .unwrap();
+  let listener = context
+    .new_node(listener_name.clone(), NodeOptions::new())
+    .unwrap();
+
+  let topic = talker
+    .create_topic(
+      &Name::new("/graph_test", "chatter").unwrap(),
+      MessageTypeName::new("std_msgs", "String"),
+      &QosPolicyBuilder::new().build(),
+    )
+    .unwrap();
+  let _publisher = talker.create_publisher::<String>(&topic, None).unwrap();
+
+  assert!(talker.get_node_names().contains(&listener_name));
+  assert!(listener.get_node_names().contains(&talker_name));
+
+  let topics = talker.get_topic_names_and_types();
+  assert_eq!(
+    topics.get("/graph_test/chatter"),
+    Some(&vec![MessageTypeName::new("std_msgs", "String")])
+  );
+}
+
+#[test]
+fn count_publishers_on_topic() {
+  let context = Context::new().unwrap();
+  let mut node = context
+    .new_node(
+      NodeName::new("/count_test", "node").unwrap(),
+      NodeOptions::new(),
+    )
+    .unwrap();
+  let scan = Name::new("/count_test", "scan").unwrap();
+  let topic = node
+    .create_topic(
+      &scan,
+      MessageTypeName::new("std_msgs", "String"),
+      &QosPolicyBuilder::new().build(),
+    )
+    .unwrap();
+  let publisher_1 = node.create_publisher::<String>(&topic, None).unwrap();
+  let _publisher_2 = node.create_publisher::<String>(&topic, None).unwrap();
+
+  assert_eq!(node.count_publishers(&scan), 2);
+  assert_eq!(node.count_subscribers(&scan), 0);
+  // Relative name in the Node namespace
+  assert_eq!(node.count_publishers(&Name::parse("scan").unwrap()), 2);
+  assert_eq!(
+    node.count_publishers(&Name::new("/count_test", "other").unwrap()),
+    0
+  );
+
+  // Dropped endpoints are no longer counted
+  drop(publisher_1);
+  assert_eq!(node.count_publishers(&scan), 1);
+
+  // Endpoints of other Nodes in the same Context are counted, and names are
+  // remapped as in create_topic.
+  let mut remapped_node = context
+    .new_node(
+      NodeName::new("/count_test", "remapped_node").unwrap(),
+      NodeOptions::new()
+        .use_global_arguments(false)
+        .cli_args(vec![
+          "--ros-args".to_string(),
+          "-r".to_string(),
+          "laser:=scan".to_string(),
+        ]),
+    )
+    .unwrap();
+  let laser = Name::new("/count_test", "laser").unwrap();
+  let remapped_topic = remapped_node
+    .create_topic(
+      &laser,
+      MessageTypeName::new("std_msgs", "String"),
+      &QosPolicyBuilder::new().build(),
+    )
+    .unwrap();
+  let _subscription = remapped_node
+    .create_subscription::<String>(&remapped_topic, None)
+    .unwrap();
+  assert_eq!(node.count_subscribers(&scan), 1);
+  assert_eq!(remapped_node.count_subscribers(&laser), 1);
+  assert_eq!(node.count_subscribers(&laser), 0);
+}
+
+#[test]
+fn matched_endpoint_count_follows_discovery() {
+  let mut matched = BTreeMap::new();
+  let local = GUID::from_bytes([1; 16]);
+  let remote = GUID::from_bytes([2; 16]);
+
+  assert_eq!(matched_endpoint_count(&matched, local), 0);
+  add_matched_endpoint(&mut matched, local, remote);
+  add_matched_endpoint(&mut matched, local, remote); // duplicate match event
+  assert_eq!(matched_endpoint_count(&matched, local), 1);
+  remove_matched_endpoint(&mut matched, remote); // remote writer lost
+  assert_eq!(matched_endpoint_count(&matched, local), 0);
+}
+
+#[test]
+fn wait_for_subscribers_counts_matches() {
+  let context = Context::new().unwrap();
+  let mut node = context
+    .new_node(
+      NodeName::new("/wait_test", "talker").unwrap(),
+      NodeOptions::new(),
+    )
+    .unwrap();
+  let topic = node
+    .create_topic(
+      &Name::new("/wait_test", "chatter").unwrap(),
+      MessageTypeName::new("std_msgs", "String"),
+      &QosPolicyBuilder::new().build(),
+    )
+    .unwrap();
+  let publisher = node.create_publisher::<String>(&topic, None).unwrap();
+
+  // Do what Spinner does on discovery events
+  let remote_event = |event: DomainParticipantStatusEvent| {
+    match event {
+      DomainParticipantStatusEvent::RemoteReaderMatched {
+        local_writer,
+        remote_reader,
+      } => add_matched_endpoint(
+        &mut node.writers_to_remote_readers.lock().unwrap(),
+        local_writer,
+        remote_reader,
+      ),
+      _ => unreachable!(),
+    }
+    for sender in node.status_event_senders.lock().unwrap().iter() {
+      sender.try_send(NodeEvent::DDS(event.clone())).unwrap();
+    }
+  };
+  let matched = |n: u8| DomainParticipantStatusEvent::RemoteReaderMatched {
+    local_writer: publisher.guid(),
+    remote_reader: GUID::from_bytes([n; 16]),
+  };
+
+  futures::executor::block_on(async {
+    let wait = publisher.wait_for_subscribers(&node, 2);
+    pin_mut!(wait);
+    assert!(futures::poll!(&mut wait).is_pending());
+    remote_event(matched(1));
+    assert!(futures::poll!(&mut wait).is_pending());
+    remote_event(matched(1)); // same one again does not count
+    assert!(futures::poll!(&mut wait).is_pending());
+    remote_event(matched(2));
+    assert!(futures::poll!(&mut wait).is_ready());
+
+    // Already matched before the call
+    assert!(futures::poll!(Box::pin(publisher.wait_for_subscribers(&node, 2))).is_ready());
+  });
+}
+
+#[test]
+fn shutdown_stops_spinner_and_removes_node() {
+  let context = Context::new().unwrap();
+  let leaving_name = NodeName::new("/shutdown_test", "leaving").unwrap();
+  let observer = context
+    .new_node(
+      NodeName::new("/shutdown_test", "observer").unwrap(),
+      NodeOptions::new(),
+    )
+    .unwrap();
+  let mut leaving = context
+    .new_node(leaving_name.clone(), NodeOptions::new())
+    .unwrap();
+  let spinner = leaving.spinner().unwrap();
+  let spin_thread = std::thread::spawn(move || smol::block_on(spinner.spin()));
+
+  assert!(observer.get_node_names().contains(&leaving_name));
+  smol::block_on(leaving.shutdown()).unwrap();
+  spin_thread.join().unwrap().unwrap(); // .spin() has returned
+  assert!(!observer.get_node_names().contains(&leaving_name));
+}
+
+#[test]
+fn function_name_is_the_enclosing_function() {
+  fn some_named_function() -> &'static str {
+    function_name!()
+  }
+  assert!(some_named_function().ends_with("::some_named_function"));
+  assert!(function_name!().ends_with("::function_name_is_the_enclosing_function"));
+  let in_closure = || function_name!();
+  assert!(in_closure().ends_with("::function_name_is_the_enclosing_function"));
+}
+
+#[test]
+#[ignore]
+fn rosout_reports_calling_function() {
+  let context = Context::new().unwrap();
+  let rosout_reader = context
+    .create_subscription::<ros_log::Log>(&context.get_rosout_topic(), None)
+    .unwrap();
+  let node = context
+    .new_node(
+      NodeName::new("/rosout_test", "logger").unwrap(),
+      NodeOptions::new().enable_rosout(true),
+    )
+    .unwrap();
+
+  fn log_something(node: &Node) {
+    rosout!(node, crate::ros2::LogLevel::Info, "Hello from a function");
+  }
+
+  let log = futures::executor::block_on(async {
+    let log_stream = rosout_reader.async_stream();
+    pin_mut!(log_stream);
+    log_something(&node);
+    loop {
+      let (log, _) = log_stream.select_next_some().await.unwrap();
+      if log.name == "logger" {
+        break log;
+      }
+    }
+  });
+  assert!(
+    log.function.ends_with("::log_something"),
+    "{}",
+    log.function
+  );
+  assert_eq!(log.msg, "Hello from a function");
+}
+
+#[test]
+fn set_parameter_respects_descriptor() {
+  let context = Context::new().unwrap();
+  let node = context
+    .new_node(
+      NodeName::new("/descriptor_test", "node").unwrap(),
+      NodeOptions::new(),
+    )
+    .unwrap();
+  let range = |from, to| ParameterDescriptor {
+    description: "Test parameter".to_string(),
+    range: Some(ParameterRange::Integer { from, to, step: 0 }),
+    ..ParameterDescriptor::default()
+  };
+
+  node
+    .declare_parameter_with_descriptor("count", ParameterValue::Integer(5), range(0, 10))
+    .unwrap();
+  assert!(node
+    .set_parameter("count", ParameterValue::Integer(10))
+    .is_ok());
+  assert!(matches!(
+    node.set_parameter("count", ParameterValue::Integer(11)),
+    Err(ParameterError::OutOfRange(_))
+  ));
+  assert_eq!(
+    node.get_parameter("count"),
+    Some(ParameterValue::Integer(10))
+  );
+
+  // Initial value must be in range, too
+  assert!(matches!(
+    node.declare_parameter_with_descriptor("other", ParameterValue::Integer(-1), range(0, 10)),
+    Err(ParameterError::OutOfRange(_))
+  ));
+
+  let read_only = ParameterDescriptor {
+    read_only: true,
+    ..ParameterDescriptor::default()
+  };
+  node
+    .declare_parameter_with_descriptor("fixed", ParameterValue::Boolean(true), read_only)
+    .unwrap();
+  assert_eq!(
+    node.set_parameter("fixed", ParameterValue::Boolean(false)),
+    Err(ParameterError::ReadOnly)
+  );
+}
+
+#[test]
+fn rosout_min_level_filters() {
+  use ros_log::LogLevel::*;
+
+  assert!(!rosout_level_passes(Debug, Info));
+  assert!(rosout_level_passes(Info, Info));
+  assert!(rosout_level_passes(Error, Info));
+  assert!(rosout_level_passes(Fatal, Info));
+  assert!(rosout_level_passes(Debug, Debug)); // default passes everything
+}
+
+#[test]
+#[ignore]
+fn rosout_min_level_suppresses_debug() {
+  let context = Context::new().unwrap();
+  let rosout_reader = context
+    .create_subscription::<ros_log::Log>(&context.get_rosout_topic(), None)
+    .unwrap();
+  let node = context
+    .new_node(
+      NodeName::new("/rosout_test", "filtered_logger").unwrap(),
+      NodeOptions::new()
+        .enable_rosout(true)
+        .rosout_min_level(crate::ros2::LogLevel::Info),
+    )
+    .unwrap();
+
+  let log = futures::executor::block_on(async {
+    let log_stream = rosout_reader.async_stream();
+    pin_mut!(log_stream);
+    rosout!(node, crate::ros2::LogLevel::Debug, "suppressed");
+    rosout!(node, crate::ros2::LogLevel::Error, "published");
+    loop {
+      let (log, _) = log_stream.select_next_some().await.unwrap();
+      if log.name == "filtered_logger" {
+        break log;
+      }
+    }
+  });
+  // The Debug message would have been received first.
+  assert_eq!(log.msg, "published");
+  assert_eq!(log.level, ros_log::Log::ERROR);
+}
+
+#[test]
+fn second_spinner_is_an_error() {
+  let context = Context::new().unwrap();
+  let mut node = context
+    .new_node(
+      NodeName::new("/spinner_test", "node").unwrap(),
+      NodeOptions::new(),
+    )
+    .unwrap();
+  let spinner = node.spinner().unwrap();
+  assert!(node.spinner().is_err());
+
+  let (done_sender, done_receiver) = std::sync::mpsc::channel();
+  std::thread::spawn(move || {
+    let result = futures::executor::block_on(spinner.spin());
+    done_sender.send(result.is_ok()).unwrap();
+  });
+  std::thread::sleep(std::time::Duration::from_millis(100));
+  assert!(node.spinner().is_err()); // still running
+
+  drop(node);
+  assert_eq!(
+    done_receiver.recv_timeout(std::time::Duration::from_secs(5)),
+    Ok(true)
+  );
+}
+
+#[test]
+fn action_server_is_listed() {
+  let context = Context::new().unwrap();
+  let mut node = context
+    .new_node(
+      NodeName::new("/action_list_test", "server_node").unwrap(),
+      NodeOptions::new(),
+    )
+    .unwrap();
+  let qos = QosPolicyBuilder::new().build();
+  let action_type = ActionTypeName::new("example_interfaces", "Fibonacci");
+  let _server = node
+    .create_action_server::<Action<i32, Vec<i32>, Vec<i32>>>(
+      ServiceMapping::Enhanced,
+      &Name::new("/action_list_test", "fibonacci").unwrap(),
+      &action_type,
+      ActionServerQosPolicies {
+        goal_service: qos.clone(),
+        result_service: qos.clone(),
+        cancel_service: qos.clone(),
+        feedback_publisher: qos.clone(),
+        status_publisher: qos,
+      },
+    )
+    .unwrap();
+
+  let actions = node.get_action_names_and_types();
+  assert_eq!(
+    actions.get("/action_list_test/fibonacci"),
+    Some(&vec![action_type])
+  );
+  // Parts of the Action are not Actions
+  assert!(actions.keys().all(|name| !name.contains("_action")));
+}
+
+#[test]
+#[ignore]
+fn spin_runs_talker_and_listener_to_completion() {
+  use crate::test_util::test_node;
+
+  let mut node = test_node("/spin_test", "node");
+  let qos = QosPolicyBuilder::new()
+    .reliability(policy::Reliability::Reliable {
+      max_blocking_time: rustdds::Duration::from_millis(100),
+    })
+    .history(policy::History::KeepAll)
+    .build();
+  let topic = node
+    .create_topic(
+      &Name::new("/spin_test", "chatter").unwrap(),
+      MessageTypeName::new("std_msgs", "String"),
+      &qos,
+    )
+    .unwrap();
+  let publisher = node.create_publisher::<String>(&topic, None).unwrap();
+  let subscription = node.create_subscription::<String>(&topic, None).unwrap();
+
+  let received = spin(&mut node, |node| async move {
+    let talker = async {
+      publisher.wait_for_subscribers(node, 1).await;
+      for i in 0..10 {
+        publisher
+          .async_publish(format!("message {i}"))
+          .await
+          .unwrap();
+      }
+    };
+    let listener = subscription
+      .async_stream()
+      .take(10)
+      .map(|r| r.unwrap().0)
+      .collect::<Vec<_>>();
+    futures::future::join(talker, listener).await.1
+  })
+  .unwrap();
+
+  assert_eq!(
+    received,
+    (0..10).map(|i| format!("message {i}")).collect::<Vec<_>>()
+  );
+  // The Spinner is gone, so a new one can be made.
+  assert!(node.spinner().is_ok());
+}
@@ -0,0 +1,648 @@
+use std::{
+  collections::BTreeMap,
+  sync::{Arc, Mutex},
+};
+
+use futures::{pin_mut, FutureExt};
+#[allow(unused_imports)]
+use log::{debug, error, info, trace, warn};
+use rustdds::{
+  dds::{CreateError, CreateResult, ReadResult},
+  *,
+};
+
+use crate::{
+  interfaces::*,
+  names::{Name, ServiceTypeName},
+  node::Node,
+  parameters::*,
+  pubsub::Publisher,
+  qos,
+  service::{AService, RmwRequestId, Server, Service, ServiceMapping},
+};
+
+// Parameter values of a Node. This is shared between the Node and its
+// ParameterServer, so that both can access the same values.
+pub(crate) struct NodeParameters {
+  node_name: String, // fully qualified, used in ParameterEvent
+  allow_undeclared: bool,
+  values: Mutex<BTreeMap<String, ParameterValue>>,
+  // Descriptors given in declaration. Lock only after values.
+  descriptors: Mutex<BTreeMap<String, ParameterDescriptor>>,
+  // Values given in NodeOptions. These take precedence over values given in
+  // declare.
+  overrides: BTreeMap<String, ParameterValue>,
+  events_writer: Publisher<raw::ParameterEvent>,
+}
+
+// Accumulates changes for a ParameterEvent
+#[derive(Default)]
+struct ParameterChanges {
+  new_parameters: Vec<raw::Parameter>,
+  changed_parameters: Vec<raw::Parameter>,
+  deleted_parameters: Vec<raw::Parameter>,
+}
+
+impl NodeParameters {
+  pub fn new(
+    node_name: String,
+    allow_undeclared: bool,
+    overrides: Vec<Parameter>,
+    events_writer: Publisher<raw::ParameterEvent>,
+  ) -> Self {
+    NodeParameters {
+      node_name,
+      allow_undeclared,
+      values: Mutex::new(BTreeMap::new()),
+      descriptors: Mutex::new(BTreeMap::new()),
+      overrides: overrides.into_iter().map(|p| (p.name, p.value)).collect(),
+      events_writer,
+    }
+  }
+
+  /// Declare all overridden parameters, using the override values.
+  pub fn declare_overrides(&self) -> Result<(), ParameterError> {
+    for (name, value) in &self.overrides {
+      self.declare(name, value.clone())?;
+    }
+    Ok(())
+  }
+
+  pub fn events_writer_guid(&self) -> GUID {
+    self.events_writer.guid()
+  }
+
+  /// If the parameter is overridden, the override value is used instead of
+  /// `value`.
+  pub fn declare(&self, name: &str, value: ParameterValue) -> Result<(), ParameterError> {
+    self.declare_with_descriptor(name, value, ParameterDescriptor::default())
+  }
+
+  /// Like `declare`, but the initial value must also be within the range of
+  /// `descriptor`.
+  pub fn declare_with_descriptor(
+    &self,
+    name: &str,
+    value: ParameterValue,
+    descriptor: ParameterDescriptor,
+  ) -> Result<(), ParameterError> {
+    let value = initial_value(&self.overrides, name, value);
+    descriptor.check_range(&value)?;
+    {
+      let mut values = self.values.lock().unwrap();
+      if values.contains_key(name) {
+        return Err(ParameterError::AlreadyDeclared);
+      }
+      values.insert(name.to_string(), value.clone());
+      self
+        .descriptors
+        .lock()
+        .unwrap()
+        .insert(name.to_string(), descriptor);
+    }
+    self.publish_event(ParameterChanges {
+      new_parameters: vec![Parameter {
+        name: name.to_string(),
+        value,
+      }
+      .into()],
+      ..ParameterChanges::default()
+    });
+    Ok(())
+  }
+
+  pub fn get(&self, name: &str) -> Option<ParameterValue> {
+    self.values.lock().unwrap().get(name).cloned()
+  }
+
+  pub fn names(&self) -> Vec<String> {
+    self.values.lock().unwrap().keys().cloned().collect()
+  }
+
+  /// Set parameters one by one. Each one succeeds or fails independently.
+  pub fn set(&self, parameters: Vec<Parameter>) -> Vec<Result<(), ParameterError>> {
+    let mut changes = ParameterChanges::default();
+    let results = {
+      let mut values = self.values.lock().unwrap();
+      parameters
+        .into_iter()
+        .map(|p| {
+          self.check(&values, &p)?;
+          Self::apply(&mut values, p, &mut changes);
+          Ok(())
+        })
+        .collect()
+    };
+    self.publish_event(changes);
+    results
+  }
+
+  /// Set parameters so that either all or none of them are set.
+  pub fn set_atomically(&self, parameters: Vec<Parameter>) -> Result<(), ParameterError> {
+    let mut changes = ParameterChanges::default();
+    {
+      let mut values = self.values.lock().unwrap();
+      for p in &parameters {
+        self.check(&values, p)?;
+      }
+      for p in parameters {
+        Self::apply(&mut values, p, &mut changes);
+      }
+    }
+    self.publish_event(changes);
+    Ok(())
+  }
+
+  fn check(
+    &self,
+    values: &BTreeMap<String, ParameterValue>,
+    p: &Parameter,
+  ) -> Result<(), ParameterError> {
+    if !values.contains_key(&p.name) && !self.allow_undeclared {
+      return Err(ParameterError::NotDeclared);
+    }
+    match self.descriptors.lock().unwrap().get(&p.name) {
+      Some(descriptor) if descriptor.read_only => Err(ParameterError::ReadOnly),
+      Some(descriptor) => descriptor.check_range(&p.value),
+      None => Ok(()),
+    }
+  }
+
+  // Setting a value of NotSet removes the parameter, like in rclcpp.
+  fn apply(
+    values: &mut BTreeMap<String, ParameterValue>,
+    p: Parameter,
+    changes: &mut ParameterChanges,
+  ) {
+    match (p.value, values.contains_key(&p.name)) {
+      (ParameterValue::NotSet, true) => {
+        if let Some(value) = values.remove(&p.name) {
+          changes.deleted_parameters.push(
+            Parameter {
+              name: p.name,
+              value,
+            }
+            .into(),
+          );
+        }
+      }
+      (ParameterValue::NotSet, false) => {} // nothing to delete
+      (value, was_declared) => {
+        values.insert(p.name.clone(), value.clone());
+        let param = Parameter {
+          name: p.name,
+          value,
+        }
+        .into();
+        if was_declared {
+          changes.changed_parameters.push(param);
+        } else {
+          changes.new_parameters.push(param);
+        }
+      }
+    }
+  }
+
+  fn publish_event(&self, changes: ParameterChanges) {
+    let ParameterChanges {
+      new_parameters,
+      changed_parameters,
+      deleted_parameters,
+    } = changes;
+    if new_parameters.is_empty() && changed_parameters.is_empty() && deleted_parameters.is_empty() {
+      return;
+    }
+    self
+      .events_writer
+      .publish(raw::ParameterEvent {
+        timestamp: Timestamp::now(),
+        node: self.node_name.clone(),
+        new_parameters,
+        changed_parameters,
+        deleted_parameters,
+      })
+      .unwrap_or_else(|e| error!("Parameter event publish failed: {e:?}"));
+  }
+
+  fn describe(&self, request: DescribeParametersRequest) -> DescribeParametersResponse {
+    let values = self.values.lock().unwrap();
+    let descriptors = self.descriptors.lock().unwrap();
+    DescribeParametersResponse {
+      descriptors: request
+        .names
+        .into_iter()
+        .map(|name| {
+          let ptype = values
+            .get(&name)
+            .map(ParameterValue::to_parameter_type)
+            .unwrap_or(raw::ParameterType::NOT_SET);
+          descriptors
+            .get(&name)
+            .cloned()
+            .unwrap_or_default()
+            .to_raw(name, ptype)
+        })
+        .collect(),
+    }
+  }
+
+  fn get_values(&self, request: GetParametersRequest) -> GetParametersResponse {
+    GetParametersResponse {
+      values: request
+        .names
+        .iter()
+        .map(|name| self.get(name).unwrap_or(ParameterValue::NotSet).into())
+        .collect(),
+    }
+  }
+
+  fn get_types(&self, request: GetParameterTypesRequest) -> GetParameterTypesResponse {
+    let values = self.values.lock().unwrap();
+    GetParameterTypesResponse {
+      types: request
+        .names
+        .iter()
+        .map(|name| {
+          values
+            .get(name)
+            .map(ParameterValue::to_parameter_type)
+            .unwrap_or(raw::ParameterType::NOT_SET)
+        })
+        .collect(),
+    }
+  }
+
+  fn list(&self, request: ListParametersRequest) -> ListParametersResponse {
+    ListParametersResponse {
+      result: list_parameters(self.names(), &request.prefixes, request.depth),
+    }
+  }
+
+  fn set_from_service(&self, request: SetParametersRequest) -> SetParametersResponse {
+    let parameters = request
+      .parameters
+      .into_iter()
+      .map(Parameter::from)
+      .collect();
+    SetParametersResponse {
+      results: self
+        .set(parameters)
+        .into_iter()
+        .map(to_set_parameters_result)
+        .collect(),
+    }
+  }
+
+  fn set_atomically_from_service(
+    &self,
+    request: SetParametersAtomicallyRequest,
+  ) -> SetParametersAtomicallyResponse {
+    let parameters = request
+      .parameters
+      .into_iter()
+      .map(Parameter::from)
+      .collect();
+    SetParametersAtomicallyResponse {
+      result: to_set_parameters_result(self.set_atomically(parameters)),
+    }
+  }
+}
+
+// Override beats the default given in declaration.
+fn initial_value(
+  overrides: &BTreeMap<String, ParameterValue>,
+  name: &str,
+  declared_default: ParameterValue,
+) -> ParameterValue {
+  overrides.get(name).cloned().unwrap_or(declared_default)
+}
+
+fn to_set_parameters_result(result: Result<(), ParameterError>) -> raw::SetParametersResult {
+  match result {
+    Ok(()) => raw::SetParametersResult {
+      successful: true,
+      reason: String::new(),
+    },
+    Err(e) => raw::SetParametersResult {
+      successful: false,
+      reason: e.to_string(),
+    },
+  }
+}
+
+// Implements the selection rules of rcl_interfaces/srv/ListParameters, same
+// as rclcpp. Parameter name components are separated by dots.
+fn list_parameters(names: Vec<String>, prefixes: &[String], depth: u64) -> ListParametersResult {
+  const SEPARATOR: char = '.';
+  let within_depth = |name: &str| {
+    depth == ListParametersRequest::DEPTH_RECURSIVE
+      || (name.matches(SEPARATOR).count() as u64) < depth
+  };
+
+  let mut result = ListParametersResult::default();
+  for name in names {
+    let get_all = prefixes.is_empty() && within_depth(&name);
+    let prefix_matches = prefixes.iter().any(|prefix| {
+      name == *prefix
+        || name
+          .strip_prefix(prefix.as_str())
+          .and_then(|rest| rest.strip_prefix(SEPARATOR))
+          .map_or(false, within_depth)
+    });
+    if get_all || prefix_matches {
+      if let Some((prefix, _)) = name.rsplit_once(SEPARATOR) {
+        if !result.prefixes.iter().any(|p| p == prefix) {
+          result.prefixes.push(prefix.to_string());
+        }
+      }
+      result.names.push(name);
+    }
+  }
+  result
+}
+
+type DescribeParametersService = AService<DescribeParametersRequest, DescribeParametersResponse>;
+type GetParametersService = AService<GetParametersRequest, GetParametersResponse>;
+type GetParameterTypesService = AService<GetParameterTypesRequest, GetParameterTypesResponse>;
+type ListParametersService = AService<ListParametersRequest, ListParametersResponse>;
+type SetParametersService = AService<SetParametersRequest, SetParametersResponse>;
+type SetParametersAtomicallyService =
+  AService<SetParametersAtomicallyRequest, SetParametersAtomicallyResponse>;
+
+/// ParameterServer serves the standard ROS 2 parameter Services of a Node.
+///
+/// It is created by [`Node::parameter_server`]. An async task should then be
+/// created to run the `.serve()` function, similarly to [`Spinner`].
+///
+/// The `.serve()` task runs until `Node` is dropped.
+///
+/// [`Spinner`]: crate::Spinner
+pub struct ParameterServer {
+  parameters: Arc<NodeParameters>,
+  stop_receiver: async_channel::Receiver<()>,
+
+  describe_server: Server<DescribeParametersService>,
+  get_server: Server<GetParametersService>,
+  get_types_server: Server<GetParameterTypesService>,
+  list_server: Server<ListParametersService>,
+  set_server: Server<SetParametersService>,
+  set_atomically_server: Server<SetParametersAtomicallyService>,
+}
+
+impl ParameterServer {
+  pub(crate) fn new(
+    node: &mut Node,
+    parameters: Arc<NodeParameters>,
+    stop_receiver: async_channel::Receiver<()>,
+  ) -> CreateResult<ParameterServer> {
+    // Parameter services are in the private namespace of the node, i.e.
+    // "~/get_parameters" etc.
+    let node_fqn = node.fully_qualified_name();
+    let private_namespace = if node_fqn.starts_with('/') {
+      node_fqn
+    } else {
+      format!("/{node_fqn}")
+    };
+    let service_name = |base_name: &str| {
+      Name::new(&private_namespace, base_name).map_err(|e| CreateError::BadParameter {
+        reason: format!("Cannot make parameter service name from {private_namespace}: {e}"),
+      })
+    };
+
+    // This is "rmw_qos_profile_parameters" from ROS 2
+    let qos = qos::parameters();
+
+    // TODO: The ServiceMapping should match what the RMW on the other side
+    // uses. Enhanced is what eProsima FastDDS, the ROS 2 default, uses.
+    let mapping = ServiceMapping::Enhanced;
+    let type_name = |name| ServiceTypeName::new("rcl_interfaces", name);
+
+    Ok(ParameterServer {
+      parameters,
+      stop_receiver,
+      describe_server: node.create_server(
+        mapping,
+        &service_name("describe_parameters")?,
+        &type_name("DescribeParameters"),
+        qos.clone(),
+        qos.clone(),
+      )?,
+      get_server: node.create_server(
+        mapping,
+        &service_name("get_parameters")?,
+        &type_name("GetParameters"),
+        qos.clone(),
+        qos.clone(),
+      )?,
+      get_types_server: node.create_server(
+        mapping,
+        &service_name("get_parameter_types")?,
+        &type_name("GetParameterTypes"),
+        qos.clone(),
+        qos.clone(),
+      )?,
+      list_server: node.create_server(
+        mapping,
+        &service_name("list_parameters")?,
+        &type_name("ListParameters"),
+        qos.clone(),
+        qos.clone(),
+      )?,
+      set_server: node.create_server(
+        mapping,
+        &service_name("set_parameters")?,
+        &type_name("SetParameters"),
+        qos.clone(),
+        qos.clone(),
+      )?,
+      set_atomically_server: node.create_server(
+        mapping,
+        &service_name("set_parameters_atomically")?,
+        &type_name("SetParametersAtomically"),
+        qos.clone(),
+        qos,
+      )?,
+    })
+  }
+
+  /// Serve parameter requests until the Node is dropped.
+  pub async fn serve(self) {
+    let params = &self.parameters;
+    loop {
+      let describe = self.describe_server.async_receive_request().fuse();
+      let get = self.get_server.async_receive_request().fuse();
+      let get_types = self.get_types_server.async_receive_request().fuse();
+      let list = self.list_server.async_receive_request().fuse();
+      let set = self.set_server.async_receive_request().fuse();
+      let set_atomically = self.set_atomically_server.async_receive_request().fuse();
+      pin_mut!(describe, get, get_types, list, set, set_atomically);
+
+      futures::select! {
+        _ = self.stop_receiver.recv().fuse() => break,
+        r = describe => respond(&self.describe_server, r, |q| params.describe(q)).await,
+        r = get => respond(&self.get_server, r, |q| params.get_values(q)).await,
+        r = get_types => respond(&self.get_types_server, r, |q| params.get_types(q)).await,
+        r = list => respond(&self.list_server, r, |q| params.list(q)).await,
+        r = set => respond(&self.set_server, r, |q| params.set_from_service(q)).await,
+        r = set_atomically => {
+          respond(&self.set_atomically_server, r, |q| params.set_atomically_from_service(q)).await
+        }
+      }
+    }
+    info!("ParameterServer exiting .serve()");
+  }
+}
+
+async fn respond<S>(
+  server: &Server<S>,
+  request: ReadResult<(RmwRequestId, S::Request)>,
+  process: impl FnOnce(S::Request) -> S::Response,
+) where
+  S: Service + 'static,
+{
+  match request {
+    Ok((req_id, request)) => server
+      .async_send_response(req_id, process(request))
+      .await
+      .unwrap_or_else(|e| error!("ParameterServer: Cannot send response: {e:?}")),
+    Err(e) => error!("ParameterServer: Cannot receive request: {e:?}"),
+  }
+}
+
+// -------------------------------------------------------------------------------------
+
+#[test]
+fn list_parameters_by_prefix_and_depth() {
+  let names = || {
+    vec![
+      "a".to_string(),
+      "b.c".to_string(),
+      "b.d.e".to_string(),
+      "bb".to_string(),
+    ]
+  };
+  let all = list_parameters(names(), &[], ListParametersRequest::DEPTH_RECURSIVE);
+  assert_eq!(all.names, names());
+  assert_eq!(all.prefixes, vec!["b".to_string(), "b.d".to_string()]);
+
+  let top_level = list_parameters(names(), &[], 1);
+  assert_eq!(top_level.names, vec!["a".to_string(), "bb".to_string()]);
+  assert!(top_level.prefixes.is_empty());
+
+  let under_b = list_parameters(names(), &["b".to_string()], 1);
+  assert_eq!(under_b.names, vec!["b.c".to_string()]);
+  assert_eq!(under_b.prefixes, vec!["b".to_string()]);
+}
+
+#[test]
+fn parameter_override_beats_declared_default() {
+  use crate::{Context, NodeName, NodeOptions};
+
+  let overrides = || {
+    vec![
+      Parameter {
+        name: "rate".to_string(),
+        value: ParameterValue::Integer(10),
+      },
+      Parameter {
+        name: "mode".to_string(),
+        value: ParameterValue::String("fast".to_string()),
+      },
+    ]
+  };
+  let context = Context::new().unwrap();
+
+  let node = context
+    .new_node(
+      NodeName::new("/override_test", "node").unwrap(),
+      NodeOptions::new().parameter_overrides(overrides()),
+    )
+    .unwrap();
+  node
+    .declare_parameter("rate", ParameterValue::Integer(1))
+    .unwrap();
+  node
+    .declare_parameter("other", ParameterValue::Boolean(true))
+    .unwrap();
+  assert_eq!(
+    node.get_parameter("rate"),
+    Some(ParameterValue::Integer(10))
+  );
+  assert_eq!(
+    node.get_parameter("other"),
+    Some(ParameterValue::Boolean(true))
+  );
+  // Not declared
+  assert_eq!(node.get_parameter("mode"), None);
+
+  let auto_node = context
+    .new_node(
+      NodeName::new("/override_test", "auto_node").unwrap(),
+      NodeOptions::new()
+        .parameter_overrides(overrides())
+        .automatically_declare_parameters_from_overrides(true),
+    )
+    .unwrap();
+  assert_eq!(
+    auto_node.get_parameter("mode"),
+    Some(ParameterValue::String("fast".to_string()))
+  );
+  assert_eq!(
+    auto_node.declare_parameter("rate", ParameterValue::Integer(1)),
+    Err(ParameterError::AlreadyDeclared)
+  );
+  assert_eq!(
+    auto_node.get_parameter("rate"),
+    Some(ParameterValue::Integer(10))
+  );
+}
+
+#[test]
+#[ignore]
+fn declared_parameter_is_read_by_get_parameters_client() {
+  use futures::future::{self, Either};
+
+  use crate::test_util::test_node;
+
+  let mut node = test_node("/get_parameters_test", "node");
+  node
+    .declare_parameter("speed", ParameterValue::Double(1.5))
+    .unwrap();
+  let parameter_server = node.parameter_server().unwrap();
+
+  let mut client_node = test_node("/get_parameters_test", "client_node");
+  let client = client_node
+    .create_client::<GetParametersService>(
+      ServiceMapping::Enhanced,
+      &Name::new("/get_parameters_test/node", "get_parameters").unwrap(),
+      &ServiceTypeName::new("rcl_interfaces", "GetParameters"),
+      qos::parameters(),
+      qos::parameters(),
+    )
+    .unwrap();
+
+  let response = crate::spin(&mut client_node, |client_node| async move {
+    let call = async {
+      client.wait_for_service(client_node).await;
+      client
+        .async_call_service(GetParametersRequest {
+          names: vec!["speed".to_string(), "unknown".to_string()],
+        })
+        .await
+        .unwrap()
+    };
+    let serve = parameter_server.serve();
+    pin_mut!(serve, call);
+    match future::select(serve, call).await {
+      Either::Left(_) => panic!("ParameterServer stopped"),
+      Either::Right((response, _)) => response,
+    }
+  })
+  .unwrap();
+
+  let values: Vec<ParameterValue> = response.values.into_iter().map(|v| v.into()).collect();
+  assert_eq!(
+    values,
+    vec![ParameterValue::Double(1.5), ParameterValue::NotSet]
+  );
+}
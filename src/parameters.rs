@@ -1,4 +1,12 @@
+use std::fmt;
+
+use futures::stream::{FusedStream, Stream, StreamExt};
+use rustdds::{dds::ReadResult, Timestamp};
+
+use crate::{names::NodeName, pubsub::Subscription};
+
 /// Rust-like representation of ROS2 Parameter
+#[derive(Debug, Clone, PartialEq)]
 pub struct Parameter {
   pub name: String,
   pub value: ParameterValue,
@@ -6,6 +14,7 @@ pub struct Parameter {
 
 /// Rust-like representation of ROS2
 /// [ParameterValue](https://github.com/ros2/rcl_interfaces/blob/master/rcl_interfaces/msg/ParameterValue.msg)
+#[derive(Debug, Clone, PartialEq)]
 pub enum ParameterValue {
   NotSet,
   Boolean(bool),
@@ -19,20 +28,155 @@ pub enum ParameterValue {
   StringArray(Vec<String>),
 }
 
+impl ParameterValue {
+  /// ROS 2 type code of the value. See [`raw::ParameterType`].
+  pub fn to_parameter_type(&self) -> u8 {
+    match self {
+      ParameterValue::NotSet => raw::ParameterType::NOT_SET,
+      ParameterValue::Boolean(_) => raw::ParameterType::BOOL,
+      ParameterValue::Integer(_) => raw::ParameterType::INTEGER,
+      ParameterValue::Double(_) => raw::ParameterType::DOUBLE,
+      ParameterValue::String(_) => raw::ParameterType::STRING,
+      ParameterValue::ByteArray(_) => raw::ParameterType::BYTE_ARRAY,
+      ParameterValue::BooleanArray(_) => raw::ParameterType::BOOL_ARRAY,
+      ParameterValue::IntegerArray(_) => raw::ParameterType::INTEGER_ARRAY,
+      ParameterValue::DoubleArray(_) => raw::ParameterType::DOUBLE_ARRAY,
+      ParameterValue::StringArray(_) => raw::ParameterType::STRING_ARRAY,
+    }
+  }
+}
+
+/// Reasons why declaring or setting a parameter may fail.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ParameterError {
+  /// Parameter with the same name has already been declared.
+  AlreadyDeclared,
+  /// Parameter has not been declared, and the Node does not allow
+  /// undeclared parameters.
+  NotDeclared,
+  /// Parameter is read-only, so it can only be set in declaration.
+  ReadOnly,
+  /// Value is not allowed by the range in the [`ParameterDescriptor`].
+  OutOfRange(String),
+}
+
+impl fmt::Display for ParameterError {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match self {
+      ParameterError::AlreadyDeclared => write!(f, "Parameter already declared"),
+      ParameterError::NotDeclared => write!(f, "Parameter not declared"),
+      ParameterError::ReadOnly => write!(f, "Parameter is read-only"),
+      ParameterError::OutOfRange(reason) => write!(f, "Parameter value out of range: {reason}"),
+    }
+  }
+}
+
+impl std::error::Error for ParameterError {}
+
+/// Description and constraints of a parameter, given in
+/// [`Node::declare_parameter_with_descriptor`](crate::Node::declare_parameter_with_descriptor).
+///
+/// This is reported by the `describe_parameters` Service.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct ParameterDescriptor {
+  pub description: String,
+  /// A read-only parameter can only be set in declaration.
+  pub read_only: bool,
+  pub range: Option<ParameterRange>,
+}
+
+/// Allowed values of a numeric parameter. Both ends are inclusive.
+///
+/// A `step` of zero allows any value in the range. Otherwise the value must be
+/// `from` plus a multiple of `step`, or `to`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ParameterRange {
+  Integer { from: i64, to: i64, step: u64 },
+  FloatingPoint { from: f64, to: f64, step: f64 },
+}
+
+impl ParameterDescriptor {
+  /// Check that `value` is allowed by `range`.
+  pub fn check_range(&self, value: &ParameterValue) -> Result<(), ParameterError> {
+    let in_range = match (&self.range, value) {
+      (None, _) | (_, ParameterValue::NotSet) => true,
+      (Some(ParameterRange::Integer { from, to, step }), ParameterValue::Integer(v)) => {
+        from <= v
+          && v <= to
+          && (*step == 0 || v == to || (*v as i128 - *from as i128) % (*step as i128) == 0)
+      }
+      (Some(ParameterRange::FloatingPoint { from, to, step }), ParameterValue::Double(v)) => {
+        let steps = (v - from) / step;
+        from <= v && v <= to && (*step == 0.0 || v == to || (steps - steps.round()).abs() < 1e-9)
+      }
+      (Some(_), _) => false, // range is for a different type
+    };
+    if in_range {
+      Ok(())
+    } else {
+      Err(ParameterError::OutOfRange(format!(
+        "{value:?} is not allowed by {:?}",
+        self.range
+      )))
+    }
+  }
+
+  pub(crate) fn to_raw(&self, name: String, ptype: u8) -> raw::ParameterDescriptor {
+    let (floating_point_range, integer_range) = match self.range {
+      None => (Vec::new(), Vec::new()),
+      Some(ParameterRange::Integer { from, to, step }) => (
+        Vec::new(),
+        vec![raw::IntegerRange {
+          from_value: from,
+          to_value: to,
+          step,
+        }],
+      ),
+      Some(ParameterRange::FloatingPoint { from, to, step }) => (
+        vec![raw::FloatingPointRange {
+          from_value: from,
+          to_value: to,
+          step,
+        }],
+        Vec::new(),
+      ),
+    };
+    raw::ParameterDescriptor {
+      name,
+      ptype,
+      description: self.description.clone(),
+      additional_constraints: String::new(),
+      read_only: self.read_only,
+      dynamic_typing: false,
+      floating_point_range,
+      integer_range,
+    }
+  }
+}
+
 impl From<raw::Parameter> for Parameter {
   fn from(rp: raw::Parameter) -> Self {
-    let pv = match rp.value.ptype {
+    Parameter {
+      name: rp.name,
+      value: rp.value.into(),
+    }
+  }
+}
+
+impl From<raw::ParameterValue> for ParameterValue {
+  fn from(rpv: raw::ParameterValue) -> Self {
+    match rpv.ptype {
       raw::ParameterType::NOT_SET => ParameterValue::NotSet,
-      raw::ParameterType::BOOL => ParameterValue::Boolean(rp.value.boolean_value),
-      raw::ParameterType::INTEGER => ParameterValue::Integer(rp.value.int_value),
-      raw::ParameterType::DOUBLE => ParameterValue::Double(rp.value.double_value),
-      raw::ParameterType::STRING => ParameterValue::String(rp.value.string_value),
+      raw::ParameterType::BOOL => ParameterValue::Boolean(rpv.boolean_value),
+      raw::ParameterType::INTEGER => ParameterValue::Integer(rpv.int_value),
+      raw::ParameterType::DOUBLE => ParameterValue::Double(rpv.double_value),
+      raw::ParameterType::STRING => ParameterValue::String(rpv.string_value),
 
-      raw::ParameterType::BYTE_ARRAY => ParameterValue::ByteArray(rp.value.byte_array),
-      raw::ParameterType::BOOL_ARRAY => ParameterValue::BooleanArray(rp.value.bool_array),
-      raw::ParameterType::INTEGER_ARRAY => ParameterValue::IntegerArray(rp.value.int_array),
-      raw::ParameterType::DOUBLE_ARRAY => ParameterValue::DoubleArray(rp.value.double_array),
-      raw::ParameterType::STRING_ARRAY => ParameterValue::StringArray(rp.value.string_array),
+      raw::ParameterType::BYTE_ARRAY => ParameterValue::ByteArray(rpv.byte_array),
+      raw::ParameterType::BOOL_ARRAY => ParameterValue::BooleanArray(rpv.bool_array),
+      raw::ParameterType::INTEGER_ARRAY => ParameterValue::IntegerArray(rpv.int_array),
+      raw::ParameterType::DOUBLE_ARRAY => ParameterValue::DoubleArray(rpv.double_array),
+      raw::ParameterType::STRING_ARRAY => ParameterValue::StringArray(rpv.string_array),
 
       _ =>
       // This may be an unspecified case.
@@ -40,19 +184,23 @@ impl From<raw::Parameter> for Parameter {
       {
         ParameterValue::NotSet
       }
-    };
-
-    Parameter {
-      name: rp.name,
-      value: pv,
     }
   }
 }
 
 impl From<Parameter> for raw::Parameter {
   fn from(p: Parameter) -> raw::Parameter {
+    raw::Parameter {
+      name: p.name,
+      value: p.value.into(),
+    }
+  }
+}
+
+impl From<ParameterValue> for raw::ParameterValue {
+  fn from(pv: ParameterValue) -> raw::ParameterValue {
     let mut value = raw::ParameterValue {
-      ptype: raw::ParameterType::NOT_SET,
+      ptype: pv.to_parameter_type(),
       boolean_value: false,
       int_value: 0,
       double_value: 0.0,
@@ -63,56 +211,87 @@ impl From<Parameter> for raw::Parameter {
       double_array: Vec::new(),
       string_array: Vec::new(),
     };
-    match p.value {
+    match pv {
       ParameterValue::NotSet => (), // already there
-      ParameterValue::Boolean(b) => {
-        value.ptype = raw::ParameterType::BOOL;
-        value.boolean_value = b;
-      }
-      ParameterValue::Integer(i) => {
-        value.ptype = raw::ParameterType::INTEGER;
-        value.int_value = i;
-      }
-      ParameterValue::Double(d) => {
-        value.ptype = raw::ParameterType::DOUBLE;
-        value.double_value = d;
-      }
-      ParameterValue::String(s) => {
-        value.ptype = raw::ParameterType::STRING;
-        value.string_value = s;
-      }
-      ParameterValue::ByteArray(a) => {
-        value.ptype = raw::ParameterType::BYTE_ARRAY;
-        value.byte_array = a;
-      }
-      ParameterValue::BooleanArray(a) => {
-        value.ptype = raw::ParameterType::BOOL_ARRAY;
-        value.bool_array = a;
-      }
-      ParameterValue::IntegerArray(a) => {
-        value.ptype = raw::ParameterType::INTEGER_ARRAY;
-        value.int_array = a;
-      }
-      ParameterValue::DoubleArray(a) => {
-        value.ptype = raw::ParameterType::DOUBLE_ARRAY;
-        value.double_array = a;
-      }
-      ParameterValue::StringArray(a) => {
-        value.ptype = raw::ParameterType::STRING_ARRAY;
-        value.string_array = a;
-      }
+      ParameterValue::Boolean(b) => value.boolean_value = b,
+      ParameterValue::Integer(i) => value.int_value = i,
+      ParameterValue::Double(d) => value.double_value = d,
+      ParameterValue::String(s) => value.string_value = s,
+      ParameterValue::ByteArray(a) => value.byte_array = a,
+      ParameterValue::BooleanArray(a) => value.bool_array = a,
+      ParameterValue::IntegerArray(a) => value.int_array = a,
+      ParameterValue::DoubleArray(a) => value.double_array = a,
+      ParameterValue::StringArray(a) => value.string_array = a,
     }
+    value
+  }
+}
 
-    raw::Parameter {
-      name: p.name,
-      value,
+/// Parameter changes of a Node, as announced on the `/parameter_events` Topic.
+///
+/// Several parameters set at once, e.g. atomically, are reported in a single
+/// event.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParameterEvent {
+  pub timestamp: Timestamp,
+  /// Fully qualified name of the Node
+  pub node: String,
+  pub new_parameters: Vec<Parameter>,
+  pub changed_parameters: Vec<Parameter>,
+  /// Parameters that were removed. Values are not meaningful.
+  pub deleted_parameters: Vec<Parameter>,
+}
+
+impl From<raw::ParameterEvent> for ParameterEvent {
+  fn from(rpe: raw::ParameterEvent) -> Self {
+    ParameterEvent {
+      timestamp: rpe.timestamp,
+      node: rpe.node,
+      new_parameters: rpe
+        .new_parameters
+        .into_iter()
+        .map(Parameter::from)
+        .collect(),
+      changed_parameters: rpe
+        .changed_parameters
+        .into_iter()
+        .map(Parameter::from)
+        .collect(),
+      deleted_parameters: rpe
+        .deleted_parameters
+        .into_iter()
+        .map(Parameter::from)
+        .collect(),
     }
   }
 }
 
-// This submodule contains raw, ROS2 -compatible Parameters.
-//
-pub(crate) mod raw {
+impl Subscription<raw::ParameterEvent> {
+  /// Stream of decoded parameter events, like `ParameterEventHandler` in
+  /// rclpy.
+  ///
+  /// If `node` is given, only events from that Node are passed on.
+  /// See [`Node::create_parameter_event_subscription`](crate::Node::create_parameter_event_subscription).
+  pub fn parameter_event_stream(
+    &self,
+    node: Option<NodeName>,
+  ) -> impl Stream<Item = ReadResult<ParameterEvent>> + FusedStream + '_ {
+    let node = node.map(|n| n.fully_qualified_name());
+    self.async_stream().filter_map(move |result| {
+      let event = match result {
+        Ok((event, _)) if node.as_ref().map_or(true, |n| *n == event.node) => {
+          Some(Ok(ParameterEvent::from(event)))
+        }
+        Ok(_) => None, // some other Node
+        Err(e) => Some(Err(e)),
+      };
+      futures::future::ready(event)
+    })
+  }
+}
+
+/// Raw, ROS2 -compatible Parameter messages.
+pub mod raw {
   use rustdds::*;
   use serde::{Deserialize, Serialize};
 
@@ -149,6 +328,43 @@ pub(crate) mod raw {
     pub string_array: Vec<String>,
   }
 
+  /// [ParameterDescriptor](https://github.com/ros2/rcl_interfaces/blob/master/rcl_interfaces/msg/ParameterDescriptor.msg)
+  #[derive(Debug, Clone, Serialize, Deserialize)]
+  pub struct ParameterDescriptor {
+    pub name: String,
+    pub ptype: u8,
+    pub description: String,
+    pub additional_constraints: String,
+    pub read_only: bool,
+    pub dynamic_typing: bool,
+    // ROS2 defines these as bounded sequences with at most one element.
+    pub floating_point_range: Vec<FloatingPointRange>,
+    pub integer_range: Vec<IntegerRange>,
+  }
+
+  /// [FloatingPointRange](https://github.com/ros2/rcl_interfaces/blob/master/rcl_interfaces/msg/FloatingPointRange.msg)
+  #[derive(Debug, Clone, Serialize, Deserialize)]
+  pub struct FloatingPointRange {
+    pub from_value: f64,
+    pub to_value: f64,
+    pub step: f64,
+  }
+
+  /// [IntegerRange](https://github.com/ros2/rcl_interfaces/blob/master/rcl_interfaces/msg/IntegerRange.msg)
+  #[derive(Debug, Clone, Serialize, Deserialize)]
+  pub struct IntegerRange {
+    pub from_value: i64,
+    pub to_value: i64,
+    pub step: u64,
+  }
+
+  /// [SetParametersResult](https://github.com/ros2/rcl_interfaces/blob/master/rcl_interfaces/msg/SetParametersResult.msg)
+  #[derive(Debug, Clone, Serialize, Deserialize)]
+  pub struct SetParametersResult {
+    pub successful: bool,
+    pub reason: String,
+  }
+
   /// ROS2 defines this as an empty .msg
   /// [ParameterType](https://github.com/ros2/rcl_interfaces/blob/master/rcl_interfaces/msg/ParameterType.msg)
   pub struct ParameterType {}
@@ -167,3 +383,131 @@ pub(crate) mod raw {
     pub const STRING_ARRAY: u8 = 9;
   }
 }
+
+// -------------------------------------------------------------------------------------
+
+#[test]
+fn parameter_range_is_checked() {
+  let integer = ParameterDescriptor {
+    range: Some(ParameterRange::Integer {
+      from: 0,
+      to: 10,
+      step: 4,
+    }),
+    ..ParameterDescriptor::default()
+  };
+  assert!(integer.check_range(&ParameterValue::Integer(0)).is_ok());
+  assert!(integer.check_range(&ParameterValue::Integer(8)).is_ok());
+  assert!(integer.check_range(&ParameterValue::Integer(10)).is_ok()); // end is allowed
+  assert!(integer.check_range(&ParameterValue::Integer(5)).is_err()); // not on step
+  assert!(integer.check_range(&ParameterValue::Integer(-4)).is_err());
+  assert!(integer.check_range(&ParameterValue::Integer(12)).is_err());
+  assert!(integer.check_range(&ParameterValue::Double(4.0)).is_err());
+
+  let double = ParameterDescriptor {
+    range: Some(ParameterRange::FloatingPoint {
+      from: -1.0,
+      to: 1.0,
+      step: 0.0,
+    }),
+    ..ParameterDescriptor::default()
+  };
+  assert!(double.check_range(&ParameterValue::Double(0.123)).is_ok());
+  assert!(double.check_range(&ParameterValue::Double(1.5)).is_err());
+
+  assert!(ParameterDescriptor::default()
+    .check_range(&ParameterValue::String("any".to_string()))
+    .is_ok());
+}
+
+#[test]
+fn parameter_event_keeps_all_changes() {
+  let raw_event = raw::ParameterEvent {
+    timestamp: Timestamp::ZERO,
+    node: "/ns/node".to_string(),
+    new_parameters: vec![Parameter {
+      name: "a".to_string(),
+      value: ParameterValue::Integer(1),
+    }
+    .into()],
+    changed_parameters: vec![
+      Parameter {
+        name: "b".to_string(),
+        value: ParameterValue::Boolean(true),
+      }
+      .into(),
+      Parameter {
+        name: "c".to_string(),
+        value: ParameterValue::String("x".to_string()),
+      }
+      .into(),
+    ],
+    deleted_parameters: vec![],
+  };
+  let event = ParameterEvent::from(raw_event);
+  assert_eq!(event.node, "/ns/node");
+  assert_eq!(
+    event.new_parameters,
+    vec![Parameter {
+      name: "a".to_string(),
+      value: ParameterValue::Integer(1)
+    }]
+  );
+  let changed: Vec<&str> = event
+    .changed_parameters
+    .iter()
+    .map(|p| p.name.as_str())
+    .collect();
+  assert_eq!(changed, vec!["b", "c"]);
+  assert!(event.deleted_parameters.is_empty());
+}
+
+#[test]
+#[ignore]
+fn parameter_change_is_observed_by_another_node() {
+  use futures::pin_mut;
+
+  use crate::{Context, NodeOptions};
+
+  let context = Context::new().unwrap();
+  let setter = context
+    .new_node(
+      NodeName::new("/param_event_test", "setter").unwrap(),
+      NodeOptions::new(),
+    )
+    .unwrap();
+  let mut watcher = context
+    .new_node(
+      NodeName::new("/param_event_test", "watcher").unwrap(),
+      NodeOptions::new(),
+    )
+    .unwrap();
+  let subscription = watcher.create_parameter_event_subscription().unwrap();
+
+  setter
+    .declare_parameter("speed", ParameterValue::Double(1.0))
+    .unwrap();
+  setter
+    .set_parameter("speed", ParameterValue::Double(2.5))
+    .unwrap();
+
+  let changed = futures::executor::block_on(async {
+    let events = subscription
+      .parameter_event_stream(Some(NodeName::new("/param_event_test", "setter").unwrap()));
+    pin_mut!(events);
+    loop {
+      let event = events.next().await.unwrap().unwrap();
+      assert_eq!(event.node, "/param_event_test/setter");
+      if !event.changed_parameters.is_empty() {
+        break event.changed_parameters;
+      }
+    }
+  });
+  assert_eq!(
+    changed,
+    vec![Parameter {
+      name: "speed".to_string(),
+      value: ParameterValue::Double(2.5)
+    }]
+  );
+}
@@ -1,17 +1,25 @@
-use std::io;
+use std::{io, marker::PhantomData};
 
 use mio::{Evented, Poll, PollOpt, Ready, Token};
+use log::warn;
 use futures::{
   pin_mut,
   stream::{FusedStream, Stream, StreamExt},
 };
 use rustdds::{
-  dds::{ReadError, ReadResult, WriteResult},
+  dds::{ReadError, ReadResult, WriteError, WriteResult},
+  serialization::deserialize_from_cdr,
   *,
 };
 use serde::{de::DeserializeOwned, Serialize};
 
-use super::{gid::Gid, message_info::MessageInfo, node::Node};
+use super::{
+  gid::Gid,
+  graph::LocalEndpointRegistration,
+  message_info::MessageInfo,
+  node::{matched_endpoint_count, MatchedEndpoints, Node},
+  xcdr2,
+};
 
 /// A ROS2 Publisher
 ///
@@ -19,18 +27,73 @@ use super::{gid::Gid, message_info::MessageInfo, node::Node};
 /// DDS
 pub struct Publisher<M: Serialize> {
   datawriter: no_key::DataWriterCdr<M>,
+  matched_subscriptions: Option<MatchedEndpoints>, // set by Node
+  _registration: Option<LocalEndpointRegistration>, // set by Context, kept until drop
 }
 
 impl<M: Serialize> Publisher<M> {
   // These must be created from Node
   pub(crate) fn new(datawriter: no_key::DataWriterCdr<M>) -> Publisher<M> {
-    Publisher { datawriter }
+    Publisher {
+      datawriter,
+      matched_subscriptions: None,
+      _registration: None,
+    }
+  }
+
+  pub(crate) fn set_registration(&mut self, registration: LocalEndpointRegistration) {
+    self._registration = Some(registration);
+  }
+
+  pub(crate) fn set_matched_subscriptions(&mut self, matched_subscriptions: MatchedEndpoints) {
+    self.matched_subscriptions = Some(matched_subscriptions);
   }
 
   pub fn publish(&self, message: M) -> WriteResult<(), M> {
     self.datawriter.write(message, Some(Timestamp::now()))
   }
 
+  /// Publish a sequence of messages, in order, all with the same source
+  /// timestamp.
+  ///
+  /// This is a convenience loop over [`Self::publish`]: each message is still
+  /// a separate DDS write, so it is neither faster nor atomic. Messages
+  /// published concurrently from other threads may be interleaved with the
+  /// batch.
+  ///
+  /// This stops at the first message that cannot be written. The error tells
+  /// how many messages were written before it, and gives back the failed
+  /// message. The rest of the sequence is not consumed.
+  pub fn publish_batch(
+    &self,
+    messages: impl IntoIterator<Item = M>,
+  ) -> Result<(), PublishBatchError<M>> {
+    let timestamp = Timestamp::now();
+    for (written, message) in messages.into_iter().enumerate() {
+      self
+        .datawriter
+        .write(message, Some(timestamp))
+        .map_err(|error| PublishBatchError { written, error })?;
+    }
+    Ok(())
+  }
+
+  /// Size of `message` in bytes, when serialized for publishing. This
+  /// includes the 4-byte encapsulation header, but not RTPS headers.
+  ///
+  /// Nothing is sent. This can be used to check large messages against the
+  /// transport MTU beforehand.
+  pub fn serialized_size(&self, message: &M) -> WriteResult<usize, ()> {
+    // Same serializer as in DataWriterCdr, which is used by publish().
+    match <CDRSerializerAdapter<M> as no_key::SerializerAdapter<M>>::to_bytes(message) {
+      Ok(bytes) => Ok(ENCAPSULATION_HEADER_SIZE + bytes.len()),
+      Err(e) => Err(WriteError::Serialization {
+        reason: e.to_string(),
+        data: (),
+      }),
+    }
+  }
+
   // pub(crate) fn publish_with_options(
   //   &self,
   //   message: M,
@@ -39,8 +102,24 @@ impl<M: Serialize> Publisher<M> {
   //   self.datawriter.write_with_options(message, wo)
   // }
 
+  /// Assert that this Publisher is alive, without publishing data.
+  ///
+  /// This is needed with Liveliness QoS kinds `ManualByTopic` and
+  /// `ManualByParticipant`, where the Publisher must either publish or assert
+  /// liveliness within the lease duration. Otherwise Subscriptions consider
+  /// it not alive.
+  ///
+  /// With `Automatic` liveliness (the default) liveliness is asserted by DDS,
+  /// so this does nothing, except log a warning.
   pub fn assert_liveliness(&self) -> WriteResult<(), ()> {
-    self.datawriter.assert_liveliness()
+    match self.datawriter.qos().liveliness() {
+      Some(policy::Liveliness::ManualByTopic { .. })
+      | Some(policy::Liveliness::ManualByParticipant { .. }) => self.datawriter.assert_liveliness(),
+      _ => {
+        warn!("assert_liveliness: Liveliness QoS is automatic. Nothing to do.");
+        Ok(())
+      }
+    }
   }
 
   pub fn guid(&self) -> rustdds::GUID {
@@ -52,11 +131,20 @@ impl<M: Serialize> Publisher<M> {
   }
 
   /// Returns the count of currently matched subscribers.
+  #[deprecated(note = "use `subscription_count()`, which does not need the Node")]
+  pub fn get_subscription_count(&self, _my_node: &Node) -> usize {
+    self.subscription_count()
+  }
+
+  /// Returns the count of currently matched Subscriptions.
   ///
-  /// `my_node` must be the Node that created this Publisher, or the result is
-  /// undefined.
-  pub fn get_subscription_count(&self, my_node: &Node) -> usize {
-    my_node.get_subscription_count(self.guid())
+  /// Matching is tracked by the [`Spinner`](crate::Spinner) of the Node that
+  /// created this Publisher, so the count is always zero if the Node is not
+  /// spinning. Subscriptions that are lost are no longer counted.
+  pub fn subscription_count(&self) -> usize {
+    self.matched_subscriptions.as_ref().map_or(0, |matched| {
+      matched_endpoint_count(&matched.lock().unwrap(), self.guid())
+    })
   }
 
   /// Waits until there is at least one matched subscription on this topic,
@@ -68,6 +156,19 @@ impl<M: Serialize> Publisher<M> {
     my_node.wait_for_reader(self.guid()).await
   }
 
+  /// Waits until at least `count` Subscriptions are matched on this topic,
+  /// possibly forever.
+  ///
+  /// Subscriptions matched already before the call are counted, and lost
+  /// Subscriptions are no longer counted. Requires the
+  /// [`Spinner`](crate::Spinner) of `my_node` to be running.
+  ///
+  /// `my_node` must be the Node that created this Publisher, or the length
+  /// of the wait is undefined.
+  pub async fn wait_for_subscribers(&self, my_node: &Node, count: usize) {
+    my_node.wait_for_readers(self.guid(), count).await
+  }
+
   pub async fn async_publish(&self, message: M) -> WriteResult<(), M> {
     self
       .datawriter
@@ -75,6 +176,22 @@ impl<M: Serialize> Publisher<M> {
       .await
   }
 
+  /// Async version of [`Self::publish_batch`]
+  pub async fn async_publish_batch(
+    &self,
+    messages: impl IntoIterator<Item = M>,
+  ) -> Result<(), PublishBatchError<M>> {
+    let timestamp = Timestamp::now();
+    for (written, message) in messages.into_iter().enumerate() {
+      self
+        .datawriter
+        .async_write(message, Some(timestamp))
+        .await
+        .map_err(|error| PublishBatchError { written, error })?;
+    }
+    Ok(())
+  }
+
   #[allow(dead_code)] // This is for async Service implementation. Remove this when it is implemented.
   pub(crate) async fn async_publish_with_options(
     &self,
@@ -84,6 +201,34 @@ impl<M: Serialize> Publisher<M> {
     self.datawriter.async_write_with_options(message, wo).await
   }
 }
+
+#[cfg(feature = "json")]
+impl<M: Serialize + DeserializeOwned> Publisher<M> {
+  /// Publish a message given as JSON, e.g. for debugging.
+  ///
+  /// The JSON value is converted to `M`, so it must have the same structure.
+  /// Requires feature "json".
+  pub fn publish_json(&self, value: serde_json::Value) -> WriteResult<(), ()> {
+    let message = serde_json::from_value::<M>(value).map_err(|e| WriteError::Serialization {
+      reason: e.to_string(),
+      data: (),
+    })?;
+    self.publish(message).map_err(|e| e.forget_data())
+  }
+}
+
+// RepresentationIdentifier and options, preceding serialized data
+const ENCAPSULATION_HEADER_SIZE: usize = 4;
+
+/// Error from [`Publisher::publish_batch`]
+#[derive(Debug)]
+pub struct PublishBatchError<M> {
+  /// Number of messages from the start of the batch that were written
+  pub written: usize,
+  /// Why the next message could not be written. Contains that message.
+  pub error: WriteError<M>,
+}
+
 // ----------------------------------------------------
 // ----------------------------------------------------
 // ----------------------------------------------------
@@ -94,14 +239,34 @@ impl<M: Serialize> Publisher<M> {
 ///
 /// Corresponds to a (simplified) [`DataReader`](rustdds::no_key::DataReader) in
 /// DDS
+///
+/// Each received sample is decoded according to its own encapsulation header,
+/// so a Subscription can receive both classic CDR and XCDR2 encoded data,
+/// even from different Publishers on the same Topic.
 pub struct Subscription<M: DeserializeOwned> {
-  datareader: no_key::SimpleDataReaderCdr<M>,
+  datareader: no_key::SimpleDataReader<M, CdrAnyVersionDeserializerAdapter<M>>,
+  matched_publishers: Option<MatchedEndpoints>, // set by Node
+  _registration: Option<LocalEndpointRegistration>, // set by Context, kept until drop
 }
 
 impl<M: 'static + DeserializeOwned> Subscription<M> {
   // These must be created from Node
-  pub(crate) fn new(datareader: no_key::SimpleDataReaderCdr<M>) -> Subscription<M> {
-    Subscription { datareader }
+  pub(crate) fn new(
+    datareader: no_key::SimpleDataReader<M, CdrAnyVersionDeserializerAdapter<M>>,
+  ) -> Subscription<M> {
+    Subscription {
+      datareader,
+      matched_publishers: None,
+      _registration: None,
+    }
+  }
+
+  pub(crate) fn set_registration(&mut self, registration: LocalEndpointRegistration) {
+    self._registration = Some(registration);
+  }
+
+  pub(crate) fn set_matched_publishers(&mut self, matched_publishers: MatchedEndpoints) {
+    self.matched_publishers = Some(matched_publishers);
   }
 
   pub fn take(&self) -> ReadResult<Option<(M, MessageInfo)>> {
@@ -110,6 +275,18 @@ impl<M: 'static + DeserializeOwned> Subscription<M> {
     Ok(ds.map(dcc_to_value_and_messageinfo))
   }
 
+  /// Take all currently available messages, in the order they were received.
+  ///
+  /// Returns an empty `Vec`, if there is nothing to take.
+  pub fn take_all(&self) -> ReadResult<Vec<(M, MessageInfo)>> {
+    self.datareader.drain_read_notifications();
+    let mut messages = Vec::new();
+    while let Some(dcc) = self.datareader.try_take_one()? {
+      messages.push(dcc_to_value_and_messageinfo(dcc));
+    }
+    Ok(messages)
+  }
+
   pub async fn async_take(&self) -> ReadResult<(M, MessageInfo)> {
     let async_stream = self.datareader.as_async_stream();
     pin_mut!(async_stream);
@@ -141,12 +318,21 @@ impl<M: 'static + DeserializeOwned> Subscription<M> {
     self.guid().into()
   }
 
+  /// Returns the count of currently matched Publishers.
+  #[deprecated(note = "use `publisher_count()`, which does not need the Node")]
+  pub fn get_publisher_count(&self, _my_node: &Node) -> usize {
+    self.publisher_count()
+  }
+
   /// Returns the count of currently matched Publishers.
   ///
-  /// `my_node` must be the Node that created this Subscription, or the result
-  /// is undefined.
-  pub fn get_publisher_count(&self, my_node: &Node) -> usize {
-    my_node.get_publisher_count(self.guid())
+  /// Matching is tracked by the [`Spinner`](crate::Spinner) of the Node that
+  /// created this Subscription, so the count is always zero if the Node is not
+  /// spinning. Publishers that are lost are no longer counted.
+  pub fn publisher_count(&self) -> usize {
+    self.matched_publishers.as_ref().map_or(0, |matched| {
+      matched_endpoint_count(&matched.lock().unwrap(), self.guid())
+    })
   }
 
   /// Waits until there is at least one matched publisher on this topic,
@@ -157,6 +343,119 @@ impl<M: 'static + DeserializeOwned> Subscription<M> {
   pub async fn wait_for_publisher(&self, my_node: &Node) {
     my_node.wait_for_writer(self.guid()).await
   }
+
+  /// Returns an async Stream of QoS events of this Subscription.
+  ///
+  /// E.g. a Publisher that is still matched, but has stopped sending, can be
+  /// detected using Deadline or Liveliness QoS policies.
+  pub fn status_stream(&self) -> impl Stream<Item = SubscriptionStatus> + FusedStream + '_ {
+    self
+      .datareader
+      .as_async_status_stream()
+      .filter_map(|status| futures::future::ready(SubscriptionStatus::from_dds(status)))
+      .fuse()
+  }
+}
+
+/// QoS events of a [`Subscription`], from [`Subscription::status_stream`].
+///
+/// Counts are cumulative over the lifetime of the Subscription, except for
+/// Liveliness, where they are the current numbers of Publishers.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum SubscriptionStatus {
+  /// Liveliness of a matched Publisher has changed.
+  LivelinessChanged {
+    alive_count: i32,
+    not_alive_count: i32,
+  },
+  /// Data was not received within the period of the Deadline QoS policy.
+  DeadlineMissed { total_count: i32 },
+  /// A Publisher was not matched, because it offered incompatible QoS.
+  RequestedIncompatibleQos { total_count: i32 },
+}
+
+impl SubscriptionStatus {
+  // Other DDS statuses are not reported.
+  fn from_dds(status: DataReaderStatus) -> Option<SubscriptionStatus> {
+    match status {
+      DataReaderStatus::LivelinessChanged {
+        alive_total,
+        not_alive_total,
+      } => Some(SubscriptionStatus::LivelinessChanged {
+        alive_count: alive_total.count(),
+        not_alive_count: not_alive_total.count(),
+      }),
+      DataReaderStatus::RequestedDeadlineMissed { count } => {
+        Some(SubscriptionStatus::DeadlineMissed {
+          total_count: count.count(),
+        })
+      }
+      DataReaderStatus::RequestedIncompatibleQos { count, .. } => {
+        Some(SubscriptionStatus::RequestedIncompatibleQos {
+          total_count: count.count(),
+        })
+      }
+      _ => None,
+    }
+  }
+}
+
+/// Deserializer adapter, which accepts both CDR (XCDR1) and XCDR2 encodings,
+/// and decides which decoder to use for each sample separately.
+pub struct CdrAnyVersionDeserializerAdapter<D> {
+  phantom: PhantomData<D>,
+}
+
+lazy_static! {
+  static ref ANY_CDR_REPR_IDS: Vec<RepresentationIdentifier> = vec![
+    RepresentationIdentifier::CDR_BE,
+    RepresentationIdentifier::CDR_LE,
+    *xcdr2::CDR2_BE,
+    *xcdr2::CDR2_LE,
+    *xcdr2::D_CDR2_BE,
+    *xcdr2::D_CDR2_LE,
+    RepresentationIdentifier::CDR2_BE,
+    RepresentationIdentifier::CDR2_LE,
+  ];
+}
+
+impl<D: DeserializeOwned> no_key::DeserializerAdapter<D> for CdrAnyVersionDeserializerAdapter<D> {
+  type Error = ReadError;
+
+  fn supported_encodings() -> &'static [RepresentationIdentifier] {
+    ANY_CDR_REPR_IDS.as_slice()
+  }
+
+  fn from_bytes(input_bytes: &[u8], encoding: RepresentationIdentifier) -> ReadResult<D> {
+    match xcdr2::Xcdr2Encoding::from_representation(encoding) {
+      Some(xcdr2_encoding) => match xcdr2::from_bytes(input_bytes, xcdr2_encoding) {
+        Ok(value) => Ok(value),
+        Err(e) => read_error_deserialization!("{}", e),
+      },
+      None => {
+        let (value, _size) = deserialize_from_cdr::<D>(input_bytes, encoding)?;
+        Ok(value)
+      }
+    }
+  }
+}
+
+#[cfg(feature = "json")]
+impl<M: 'static + DeserializeOwned + Serialize> Subscription<M> {
+  /// Take a message, if available, converted to JSON. This is similar to
+  /// `ros2 topic echo`.
+  ///
+  /// The message type must still be known, because messages are
+  /// deserialized as `M` first. Requires feature "json".
+  pub fn take_json(&self) -> ReadResult<Option<(serde_json::Value, MessageInfo)>> {
+    match self.take()? {
+      None => Ok(None),
+      Some((message, info)) => match serde_json::to_value(message) {
+        Ok(value) => Ok(Some((value, info))),
+        Err(e) => read_error_deserialization!("Cannot convert message to JSON: {}", e),
+      },
+    }
+  }
 }
 
 // helper
@@ -193,3 +492,755 @@ where
     self.datareader.deregister(poll)
   }
 }
+
+// ----------------------------------------------------
+// ----------------------------------------------------
+
+/// A ROS2 Publisher on a keyed Topic
+///
+/// Corresponds to a simplified [`DataWriter`](rustdds::with_key::DataWriter) in
+/// DDS. Each distinct key value of the message type identifies a separate
+/// instance of data on the same Topic.
+///
+/// Create these with [`Node::create_keyed_publisher`] on a Topic from
+/// [`Node::create_keyed_topic`].
+///
+/// Unlike a plain [`Publisher`], this can also signal that an instance is gone,
+/// using [`Self::dispose`] or [`Self::unregister_instance`]. Plain Publishers
+/// have no instances, so they have no such operations.
+pub struct KeyedPublisher<M>
+where
+  M: Keyed + Serialize,
+  M::K: Key,
+{
+  datawriter: with_key::DataWriter<M, CDRSerializerAdapter<M>>,
+  matched_subscriptions: Option<MatchedEndpoints>, // set by Node
+  _registration: Option<LocalEndpointRegistration>, // set by Context, kept until drop
+}
+
+impl<M> KeyedPublisher<M>
+where
+  M: Keyed + Serialize,
+  M::K: Key,
+{
+  // These must be created from Node
+  pub(crate) fn new(
+    datawriter: with_key::DataWriter<M, CDRSerializerAdapter<M>>,
+  ) -> KeyedPublisher<M> {
+    KeyedPublisher {
+      datawriter,
+      matched_subscriptions: None,
+      _registration: None,
+    }
+  }
+
+  pub(crate) fn set_registration(&mut self, registration: LocalEndpointRegistration) {
+    self._registration = Some(registration);
+  }
+
+  pub(crate) fn set_matched_subscriptions(&mut self, matched_subscriptions: MatchedEndpoints) {
+    self.matched_subscriptions = Some(matched_subscriptions);
+  }
+
+  pub fn publish(&self, message: M) -> WriteResult<(), M> {
+    self.datawriter.write(message, Some(Timestamp::now()))
+  }
+
+  pub async fn async_publish(&self, message: M) -> WriteResult<(), M> {
+    self
+      .datawriter
+      .async_write(message, Some(Timestamp::now()))
+      .await
+  }
+
+  /// Dispose the instance identified by `key`, i.e. declare that it no
+  /// longer exists.
+  ///
+  /// Subscriptions will see instance state
+  /// [`NotAliveDisposed`](rustdds::InstanceState::NotAliveDisposed).
+  pub fn dispose(&self, key: &M::K) -> WriteResult<(), ()> {
+    self.datawriter.dispose(key, Some(Timestamp::now()))
+  }
+
+  /// Declare that this Publisher will no longer write the instance identified
+  /// by `key`. The instance may still be written by other Publishers.
+  ///
+  /// If there are no other Publishers, Subscriptions will see instance state
+  /// [`NotAliveNoWriters`](rustdds::InstanceState::NotAliveNoWriters).
+  pub fn unregister_instance(&self, key: &M::K) -> WriteResult<(), ()> {
+    self
+      .datawriter
+      .unregister_instance(key, Some(Timestamp::now()))
+  }
+
+  pub fn assert_liveliness(&self) -> WriteResult<(), ()> {
+    self.datawriter.assert_liveliness()
+  }
+
+  /// Returns the count of currently matched Subscriptions, as in
+  /// [`Publisher::subscription_count`].
+  pub fn subscription_count(&self) -> usize {
+    self.matched_subscriptions.as_ref().map_or(0, |matched| {
+      matched_endpoint_count(&matched.lock().unwrap(), self.guid())
+    })
+  }
+
+  pub fn guid(&self) -> rustdds::GUID {
+    self.datawriter.guid()
+  }
+
+  pub fn gid(&self) -> Gid {
+    self.guid().into()
+  }
+}
+
+/// A ROS2 Subscription on a keyed Topic
+///
+/// Corresponds to a [`DataReader`](rustdds::with_key::DataReader) in DDS.
+///
+/// Create these with [`Node::create_keyed_subscription`] on a Topic from
+/// [`Node::create_keyed_topic`].
+pub struct KeyedSubscription<M>
+where
+  M: Keyed + DeserializeOwned,
+  M::K: Key,
+{
+  datareader: with_key::DataReader<M, CDRDeserializerAdapter<M>>,
+  matched_publishers: Option<MatchedEndpoints>, // set by Node
+  _registration: Option<LocalEndpointRegistration>, // set by Context, kept until drop
+}
+
+impl<M> KeyedSubscription<M>
+where
+  M: 'static + Keyed + DeserializeOwned,
+  M::K: Key,
+{
+  // These must be created from Node
+  pub(crate) fn new(
+    datareader: with_key::DataReader<M, CDRDeserializerAdapter<M>>,
+  ) -> KeyedSubscription<M> {
+    KeyedSubscription {
+      datareader,
+      matched_publishers: None,
+      _registration: None,
+    }
+  }
+
+  pub(crate) fn set_registration(&mut self, registration: LocalEndpointRegistration) {
+    self._registration = Some(registration);
+  }
+
+  pub(crate) fn set_matched_publishers(&mut self, matched_publishers: MatchedEndpoints) {
+    self.matched_publishers = Some(matched_publishers);
+  }
+
+  /// Take the next available sample.
+  ///
+  /// The sample is `Ok(message)` for data, or `Err(key)`, if the instance
+  /// identified by `key` is no longer alive.
+  pub fn take(&mut self) -> ReadResult<Option<(Result<M, M::K>, MessageInfo)>> {
+    Ok(self.datareader.take_next_sample()?.map(|ds| {
+      let mi = MessageInfo::from(ds.sample_info());
+      (ds.into_value(), mi)
+    }))
+  }
+
+  /// Returns the count of currently matched Publishers, as in
+  /// [`Subscription::publisher_count`].
+  pub fn publisher_count(&self) -> usize {
+    self.matched_publishers.as_ref().map_or(0, |matched| {
+      matched_endpoint_count(&matched.lock().unwrap(), self.guid())
+    })
+  }
+
+  pub fn guid(&self) -> rustdds::GUID {
+    self.datareader.guid()
+  }
+
+  pub fn gid(&self) -> Gid {
+    self.guid().into()
+  }
+}
+
+impl<M> Evented for KeyedSubscription<M>
+where
+  M: Keyed + DeserializeOwned,
+  M::K: Key,
+{
+  fn register(&self, poll: &Poll, token: Token, interest: Ready, opts: PollOpt) -> io::Result<()> {
+    self.datareader.register(poll, token, interest, opts)
+  }
+
+  fn reregister(
+    &self,
+    poll: &Poll,
+    token: Token,
+    interest: Ready,
+    opts: PollOpt,
+  ) -> io::Result<()> {
+    self.datareader.reregister(poll, token, interest, opts)
+  }
+
+  fn deregister(&self, poll: &Poll) -> io::Result<()> {
+    self.datareader.deregister(poll)
+  }
+}
+
+// -------------------------------------------------------------------------------------
+
+#[cfg(test)]
+#[derive(Clone, Debug, PartialEq, serde::Deserialize)]
+struct TestMessage {
+  flag: u8,
+  value: f64,
+  names: Vec<String>,
+}
+
+#[test]
+fn subscription_decodes_cdr_and_xcdr2() {
+  use no_key::DeserializerAdapter;
+
+  let expected = TestMessage {
+    flag: 1,
+    value: 2.5,
+    names: vec!["x".to_string()],
+  };
+
+  // Classic CDR: f64 is aligned to 8, no DHEADER on sequences.
+  let mut cdr = vec![1u8, 0, 0, 0, 0, 0, 0, 0];
+  cdr.extend(2.5f64.to_le_bytes());
+  cdr.extend(1u32.to_le_bytes());
+  cdr.extend(2u32.to_le_bytes());
+  cdr.extend(b"x\0");
+
+  // XCDR2: f64 is aligned to 4, sequence of strings has a DHEADER.
+  let mut xcdr2_bytes = vec![1u8, 0, 0, 0];
+  xcdr2_bytes.extend(2.5f64.to_le_bytes());
+  xcdr2_bytes.extend(10u32.to_le_bytes());
+  xcdr2_bytes.extend(1u32.to_le_bytes());
+  xcdr2_bytes.extend(2u32.to_le_bytes());
+  xcdr2_bytes.extend(b"x\0");
+
+  type Adapter = CdrAnyVersionDeserializerAdapter<TestMessage>;
+  assert_eq!(
+    Adapter::from_bytes(&cdr, RepresentationIdentifier::CDR_LE).unwrap(),
+    expected
+  );
+  assert_eq!(
+    Adapter::from_bytes(&xcdr2_bytes, *xcdr2::CDR2_LE).unwrap(),
+    expected
+  );
+
+  // The writer of the end-to-end test produces the same
+  use no_key::SerializerAdapter;
+  assert_eq!(
+    Xcdr2TestSerializerAdapter::to_bytes(&expected).unwrap(),
+    xcdr2_bytes
+  );
+}
+
+// Writes TestMessages in XCDR2, which RustDDS itself cannot do.
+#[cfg(test)]
+struct Xcdr2TestSerializerAdapter;
+
+#[cfg(test)]
+impl no_key::SerializerAdapter<TestMessage> for Xcdr2TestSerializerAdapter {
+  type Error = WriteError<()>;
+  fn output_encoding() -> RepresentationIdentifier {
+    *xcdr2::CDR2_LE
+  }
+
+  fn to_bytes(message: &TestMessage) -> WriteResult<bytes::Bytes, ()> {
+    let mut bytes = vec![message.flag, 0, 0, 0];
+    bytes.extend(message.value.to_le_bytes());
+    let mut names = Vec::new();
+    names.extend((message.names.len() as u32).to_le_bytes());
+    for name in &message.names {
+      while names.len() % 4 != 0 {
+        names.push(0); // align string length
+      }
+      names.extend((name.len() as u32 + 1).to_le_bytes());
+      names.extend(name.as_bytes());
+      names.push(0);
+    }
+    bytes.extend((names.len() as u32).to_le_bytes()); // DHEADER
+    bytes.extend(names);
+    Ok(bytes.into())
+  }
+}
+
+#[test]
+#[ignore]
+fn subscription_receives_xcdr2_sample() {
+  use crate::{
+    test_util::{reliable_qos, test_node},
+    MessageTypeName, Name,
+  };
+
+  let mut node = test_node("/xcdr2_test", "node");
+  let topic = node
+    .create_topic(
+      &Name::new("/xcdr2_test", "messages").unwrap(),
+      MessageTypeName::new("xcdr2_test", "TestMessage"),
+      &reliable_qos(),
+    )
+    .unwrap();
+  let subscription = node
+    .create_subscription::<TestMessage>(&topic, None)
+    .unwrap();
+  let writer = node
+    .create_datawriter::<TestMessage, Xcdr2TestSerializerAdapter>(&topic, None)
+    .unwrap();
+
+  let message = TestMessage {
+    flag: 1,
+    value: 2.5,
+    names: vec!["x".to_string(), "yz".to_string()],
+  };
+  writer.write(message.clone(), None).unwrap();
+  let mut received = None;
+  for _ in 0..50 {
+    received = subscription.take().unwrap();
+    if received.is_some() {
+      break;
+    }
+    std::thread::sleep(std::time::Duration::from_millis(100));
+  }
+  assert_eq!(received.map(|(m, _)| m), Some(message));
+}
+
+#[test]
+fn serialized_size_matches_cdr() {
+  use crate::{test_util::test_node, MessageTypeName, Name};
+
+  #[derive(Serialize)]
+  struct Sample {
+    a: u8,
+    b: u32,
+    s: String,
+  }
+
+  let mut node = test_node("/size_test", "node");
+  let topic = node
+    .create_topic(
+      &Name::new("/size_test", "samples").unwrap(),
+      MessageTypeName::new("size_test", "Sample"),
+      &QosPolicyBuilder::new().build(),
+    )
+    .unwrap();
+  let publisher = node.create_publisher::<Sample>(&topic, None).unwrap();
+
+  for s in ["", "hi", "a somewhat longer string"] {
+    let sample = Sample {
+      a: 1,
+      b: 2,
+      s: s.to_string(),
+    };
+    let cdr =
+      <CDRSerializerAdapter<Sample> as no_key::SerializerAdapter<Sample>>::to_bytes(&sample)
+        .unwrap();
+    // The serialized data, and the 4-byte encapsulation header before it
+    assert_eq!(publisher.serialized_size(&sample).unwrap(), cdr.len() + 4);
+  }
+}
+
+#[test]
+#[ignore]
+fn status_stream_reports_missed_deadline() {
+  use crate::test_util::{int32_topic, test_node};
+
+  let mut node = test_node("/deadline_test", "node");
+  let qos = QosPolicyBuilder::new()
+    .reliability(policy::Reliability::Reliable {
+      max_blocking_time: rustdds::Duration::from_millis(100),
+    })
+    .deadline(policy::Deadline(rustdds::Duration::from_millis(200)))
+    .build();
+  let topic = int32_topic(&mut node, "heartbeat", &qos);
+  let subscription = node.create_subscription::<i32>(&topic, None).unwrap();
+  let publisher = node.create_publisher::<i32>(&topic, None).unwrap();
+
+  publisher.publish(1).unwrap();
+  // ... and then silence
+
+  let status = futures::executor::block_on(async {
+    let status_stream = subscription.status_stream();
+    pin_mut!(status_stream);
+    loop {
+      match status_stream.next().await.unwrap() {
+        status @ SubscriptionStatus::DeadlineMissed { .. } => break status,
+        _other => {}
+      }
+    }
+  });
+  assert!(matches!(
+    status,
+    SubscriptionStatus::DeadlineMissed { total_count } if total_count >= 1
+  ));
+}
+
+#[test]
+#[ignore]
+fn assert_liveliness_keeps_publisher_alive() {
+  use crate::test_util::{int32_topic, test_node};
+
+  let mut node = test_node("/liveliness_test", "node");
+  let qos = QosPolicyBuilder::new()
+    .reliability(policy::Reliability::Reliable {
+      max_blocking_time: rustdds::Duration::from_millis(100),
+    })
+    .liveliness(policy::Liveliness::ManualByTopic {
+      lease_duration: rustdds::Duration::from_millis(500),
+    })
+    .build();
+  let topic = int32_topic(&mut node, "alive", &qos);
+  let subscription = node.create_subscription::<i32>(&topic, None).unwrap();
+  let publisher = node.create_publisher::<i32>(&topic, None).unwrap();
+
+  let status = futures::executor::block_on(async {
+    let status_stream = subscription.status_stream();
+    pin_mut!(status_stream);
+    publisher.assert_liveliness().unwrap();
+    loop {
+      match status_stream.next().await.unwrap() {
+        status @ SubscriptionStatus::LivelinessChanged { .. } => break status,
+        _other => {}
+      }
+    }
+  });
+  assert!(matches!(
+    status,
+    SubscriptionStatus::LivelinessChanged { alive_count: 1, .. }
+  ));
+}
+
+#[test]
+#[ignore]
+fn publisher_count_follows_remote_publisher() {
+  use crate::test_util::{int32_topic, reliable_qos, test_node};
+
+  // Polls `count` until it reaches `expected`, for at most 10 seconds.
+  async fn wait_for_count(count: impl Fn() -> usize, expected: usize) -> usize {
+    for _ in 0..100 {
+      if count() == expected {
+        break;
+      }
+      crate::runtime::sleep(std::time::Duration::from_millis(100)).await;
+    }
+    count()
+  }
+
+  let qos = reliable_qos();
+  let mut node = test_node("/publisher_count_test", "listener");
+  let topic = int32_topic(&mut node, "numbers", &qos);
+  let subscription = node.create_subscription::<i32>(&topic, None).unwrap();
+
+  let counts = crate::spin(&mut node, |_node| async move {
+    let before = subscription.publisher_count();
+
+    // The remote Node is in another Context, i.e. DomainParticipant.
+    let mut remote_node = test_node("/publisher_count_test", "talker");
+    let remote_topic = int32_topic(&mut remote_node, "numbers", &qos);
+    let publisher = remote_node
+      .create_publisher::<i32>(&remote_topic, None)
+      .unwrap();
+    let with_publisher = wait_for_count(|| subscription.publisher_count(), 1).await;
+
+    drop(publisher);
+    drop(remote_node);
+    let after = wait_for_count(|| subscription.publisher_count(), 0).await;
+    (before, with_publisher, after)
+  })
+  .unwrap();
+
+  assert_eq!(counts, (0, 1, 0));
+}
+
+#[test]
+#[ignore]
+fn take_all_returns_all_available_in_order() {
+  use crate::test_util::{int32_topic, reliable_qos, test_node};
+
+  let mut node = test_node("/take_all_test", "node");
+  let qos = reliable_qos();
+  let topic = int32_topic(&mut node, "numbers", &qos);
+  let subscription = node.create_subscription::<i32>(&topic, None).unwrap();
+  let publisher = node.create_publisher::<i32>(&topic, None).unwrap();
+
+  assert!(subscription.take_all().unwrap().is_empty());
+
+  for i in 0..5 {
+    publisher.publish(i).unwrap();
+  }
+  std::thread::sleep(std::time::Duration::from_millis(500));
+
+  let numbers: Vec<i32> = subscription
+    .take_all()
+    .unwrap()
+    .into_iter()
+    .map(|(n, _)| n)
+    .collect();
+  assert_eq!(numbers, vec![0, 1, 2, 3, 4]);
+  assert!(subscription.take_all().unwrap().is_empty());
+}
+
+#[test]
+#[ignore]
+fn keyed_topic_carries_two_instances() {
+  use crate::{
+    test_util::{reliable_qos, test_node, Reading},
+    MessageTypeName, Name,
+  };
+
+  let mut node = test_node("/keyed_test", "node");
+  let qos = reliable_qos();
+  let topic = node
+    .create_keyed_topic(
+      &Name::new("/keyed_test", "readings").unwrap(),
+      MessageTypeName::new("keyed_test", "Reading"),
+      &qos,
+    )
+    .unwrap();
+  let mut subscription = node
+    .create_keyed_subscription::<Reading>(&topic, None)
+    .unwrap();
+  let publisher = node
+    .create_keyed_publisher::<Reading>(&topic, None)
+    .unwrap();
+
+  publisher
+    .publish(Reading {
+      sensor: 1,
+      value: 10,
+    })
+    .unwrap();
+  publisher
+    .publish(Reading {
+      sensor: 2,
+      value: 20,
+    })
+    .unwrap();
+  std::thread::sleep(std::time::Duration::from_millis(500));
+
+  let mut received = Vec::new();
+  while let Some((sample, _)) = subscription.take().unwrap() {
+    received.push(sample.unwrap());
+  }
+  received.sort_by_key(|r| r.sensor);
+  assert_eq!(
+    received,
+    vec![
+      Reading {
+        sensor: 1,
+        value: 10
+      },
+      Reading {
+        sensor: 2,
+        value: 20
+      },
+    ]
+  );
+}
+
+#[test]
+#[ignore]
+fn keyed_subscription_sees_disposed_instance() {
+  use crate::{
+    test_util::{reliable_qos, test_node, Reading},
+    MessageTypeName, Name,
+  };
+
+  let mut node = test_node("/dispose_test", "node");
+  let qos = reliable_qos();
+  let topic = node
+    .create_keyed_topic(
+      &Name::new("/dispose_test", "readings").unwrap(),
+      MessageTypeName::new("dispose_test", "Reading"),
+      &qos,
+    )
+    .unwrap();
+  let mut subscription = node
+    .create_keyed_subscription::<Reading>(&topic, None)
+    .unwrap();
+  let publisher = node
+    .create_keyed_publisher::<Reading>(&topic, None)
+    .unwrap();
+
+  publisher
+    .publish(Reading {
+      sensor: 7,
+      value: 70,
+    })
+    .unwrap();
+  std::thread::sleep(std::time::Duration::from_millis(500));
+  let (sample, info) = subscription.take().unwrap().unwrap();
+  assert_eq!(sample.unwrap().sensor, 7);
+  assert_eq!(info.instance_state(), InstanceState::Alive);
+
+  publisher.dispose(&7).unwrap();
+  std::thread::sleep(std::time::Duration::from_millis(500));
+  let (sample, info) = subscription.take().unwrap().unwrap();
+  assert_eq!(sample.unwrap_err(), 7);
+  assert_eq!(info.instance_state(), InstanceState::NotAliveDisposed);
+}
+
+#[test]
+#[ignore]
+fn publish_batch_arrives_in_order() {
+  use crate::test_util::{int32_topic, test_node};
+
+  const COUNT: i32 = 1000;
+
+  let mut node = test_node("/batch_test", "node");
+  let qos = QosPolicyBuilder::new()
+    .reliability(policy::Reliability::Reliable {
+      max_blocking_time: rustdds::Duration::from_millis(100),
+    })
+    .history(policy::History::KeepAll)
+    .build();
+  let topic = int32_topic(&mut node, "numbers", &qos);
+  let subscription = node.create_subscription::<i32>(&topic, None).unwrap();
+  let publisher = node.create_publisher::<i32>(&topic, None).unwrap();
+
+  publisher.publish_batch(0..COUNT).unwrap();
+
+  let (received, timestamps): (Vec<i32>, Vec<_>) = futures::executor::block_on(async {
+    let stream = subscription.async_stream();
+    pin_mut!(stream);
+    let mut received = Vec::new();
+    while received.len() < COUNT as usize {
+      let (n, info) = stream.next().await.unwrap().unwrap();
+      received.push((n, info.source_timestamp()));
+    }
+    received.into_iter().unzip()
+  });
+  assert_eq!(received, (0..COUNT).collect::<Vec<_>>());
+  assert!(timestamps
+    .iter()
+    .all(|t| t.is_some() && *t == timestamps[0]));
+}
+
+#[test]
+#[ignore]
+fn message_info_identifies_publisher() {
+  use crate::test_util::{int32_topic, reliable_qos, test_node};
+
+  let mut node = test_node("/info_test", "node");
+  let qos = reliable_qos();
+  let topic = int32_topic(&mut node, "numbers", &qos);
+  let subscription = node.create_subscription::<i32>(&topic, None).unwrap();
+  let publisher = node.create_publisher::<i32>(&topic, None).unwrap();
+
+  let received = futures::executor::block_on(async {
+    let stream = subscription.async_stream();
+    pin_mut!(stream);
+    publisher.publish(1).unwrap();
+    publisher.publish(2).unwrap();
+    let first = stream.next().await.unwrap().unwrap();
+    let second = stream.next().await.unwrap().unwrap();
+    [first, second]
+  });
+  for (_, info) in &received {
+    assert_eq!(info.writer_guid(), publisher.guid());
+    assert_eq!(info.writer_gid(), publisher.gid());
+    assert!(info.source_timestamp().is_some());
+  }
+  assert!(
+    received[0].1.publication_sequence_number() < received[1].1.publication_sequence_number()
+  );
+}
+
+// Needs feature "json": `cargo test --features json -- --ignored`
+#[cfg(feature = "json")]
+#[test]
+#[ignore]
+fn json_round_trip() {
+  use serde::Deserialize;
+
+  use crate::{
+    test_util::{reliable_qos, test_node},
+    MessageTypeName, Name,
+  };
+
+  // Like std_msgs/String
+  #[derive(Serialize, Deserialize)]
+  struct StringMessage {
+    data: String,
+  }
+
+  let mut node = test_node("/json_test", "node");
+  let qos = reliable_qos();
+  let topic = node
+    .create_topic(
+      &Name::new("/json_test", "chatter").unwrap(),
+      MessageTypeName::new("std_msgs", "String"),
+      &qos,
+    )
+    .unwrap();
+  let subscription = node
+    .create_subscription::<StringMessage>(&topic, None)
+    .unwrap();
+  let publisher = node
+    .create_publisher::<StringMessage>(&topic, None)
+    .unwrap();
+
+  let value = serde_json::json!({ "data": "Hello" });
+  publisher.publish_json(value.clone()).unwrap();
+  assert!(publisher
+    .publish_json(serde_json::json!({ "wrong": 1 }))
+    .is_err());
+  std::thread::sleep(std::time::Duration::from_millis(500));
+
+  let (received, _info) = subscription.take_json().unwrap().unwrap();
+  assert_eq!(received, value);
+  assert!(subscription.take_json().unwrap().is_none());
+}
+
+#[test]
+#[ignore]
+fn keyed_endpoints_count_remote_matches() {
+  use crate::{
+    test_util::{reliable_qos, test_node, Reading},
+    MessageTypeName, Name,
+  };
+
+  let qos = reliable_qos();
+  let keyed_topic = |node: &mut Node| {
+    node
+      .create_keyed_topic(
+        &Name::new("/keyed_count_test", "readings").unwrap(),
+        MessageTypeName::new("keyed_test", "Reading"),
+        &qos,
+      )
+      .unwrap()
+  };
+  let mut node = test_node("/keyed_count_test", "listener");
+  let topic = keyed_topic(&mut node);
+  let subscription = node
+    .create_keyed_subscription::<Reading>(&topic, None)
+    .unwrap();
+
+  let counts = crate::spin(&mut node, |_node| async move {
+    // The remote Node is in another Context, i.e. DomainParticipant.
+    let mut remote_node = test_node("/keyed_count_test", "talker");
+    let remote_topic = keyed_topic(&mut remote_node);
+    let publisher = remote_node
+      .create_keyed_publisher::<Reading>(&remote_topic, None)
+      .unwrap();
+    let remote_spinner = remote_node.spinner().unwrap();
+    std::thread::spawn(move || smol::block_on(remote_spinner.spin()));
+    for _ in 0..100 {
+      if subscription.publisher_count() == 1 && publisher.subscription_count() == 1 {
+        break;
+      }
+      crate::runtime::sleep(std::time::Duration::from_millis(100)).await;
+    }
+    (
+      subscription.publisher_count(),
+      publisher.subscription_count(),
+    )
+  })
+  .unwrap();
+
+  assert_eq!(counts, (1, 1));
+}
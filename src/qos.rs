@@ -0,0 +1,172 @@
+//! Standard ROS 2 QoS profiles
+//!
+//! These match the profiles defined in
+//! [rmw/qos_profiles.h](https://github.com/ros2/rmw/blob/rolling/rmw/include/rmw/qos_profiles.h),
+//! so that e.g. a Subscription using [`sensor_data`] is compatible with a
+//! C++ or Python Publisher using the same profile.
+//!
+//! Policies that the ROS 2 profiles leave to "system default" are not set, so
+//! DDS defaults apply.
+
+use rustdds::{
+  policy::{Deadline, Durability, History, Reliability},
+  Duration, QosPolicies, QosPolicyBuilder,
+};
+
+fn reliable() -> Reliability {
+  Reliability::Reliable {
+    max_blocking_time: Duration::from_millis(100),
+  }
+}
+
+/// For sensor data, where timeliness is more important than getting every
+/// sample: best effort, keep last 5, volatile.
+pub fn sensor_data() -> QosPolicies {
+  QosPolicyBuilder::new()
+    .reliability(Reliability::BestEffort)
+    .history(History::KeepLast { depth: 5 })
+    .durability(Durability::Volatile)
+    .build()
+}
+
+/// For parameter Services: reliable, keep last 1000, volatile.
+pub fn parameters() -> QosPolicies {
+  QosPolicyBuilder::new()
+    .reliability(reliable())
+    .history(History::KeepLast { depth: 1000 })
+    .durability(Durability::Volatile)
+    .build()
+}
+
+/// For Services in general: reliable, keep last 10, volatile.
+pub fn services_default() -> QosPolicies {
+  QosPolicyBuilder::new()
+    .reliability(reliable())
+    .history(History::KeepLast { depth: 10 })
+    .durability(Durability::Volatile)
+    .build()
+}
+
+/// For the `/parameter_events` Topic: reliable, keep last 1000, volatile.
+pub fn parameter_events() -> QosPolicies {
+  QosPolicyBuilder::new()
+    .reliability(reliable())
+    .history(History::KeepLast { depth: 1000 })
+    .durability(Durability::Volatile)
+    .build()
+}
+
+/// Leave all policies to DDS defaults.
+pub fn system_default() -> QosPolicies {
+  QosPolicyBuilder::new().build()
+}
+
+/// Check if a Subscription `requested` QoS can ever match a Publisher with
+/// `offered` QoS, according to the DDS request-offered rules. Reliability,
+/// durability and deadline are checked. Policies that are not set are taken
+/// to have their DDS default values: Reliable for a Publisher and BestEffort
+/// for a Subscription, Volatile durability, and no deadline.
+///
+/// Returns a description of each incompatibility, i.e. empty if compatible.
+pub fn incompatibilities(offered: &QosPolicies, requested: &QosPolicies) -> Vec<String> {
+  let mut problems = Vec::new();
+  if let (Some(Reliability::BestEffort), Some(Reliability::Reliable { .. })) =
+    (offered.reliability(), requested.reliability())
+  {
+    problems.push("Reliable reliability requested, but only BestEffort offered".to_string());
+  }
+  if let Some(r) = requested.durability() {
+    let o = offered.durability().unwrap_or(Durability::Volatile);
+    if durability_rank(o) < durability_rank(r) {
+      problems.push(format!(
+        "Durability {r:?} requested, but only {o:?} offered"
+      ));
+    }
+  }
+  if let Some(Deadline(r)) = requested.deadline() {
+    match offered.deadline() {
+      Some(Deadline(o)) if o <= r => {}
+      Some(Deadline(o)) => {
+        problems.push(format!("Deadline {r:?} requested, but only {o:?} offered"))
+      }
+      None => problems.push(format!("Deadline {r:?} requested, but no deadline offered")),
+    }
+  }
+  problems
+}
+
+// Order of durability kinds, from weakest to strongest
+fn durability_rank(durability: Durability) -> u8 {
+  match durability {
+    Durability::Volatile => 0,
+    Durability::TransientLocal => 1,
+    Durability::Transient => 2,
+    Durability::Persistent => 3,
+  }
+}
+
+// -------------------------------------------------------------------------------------
+
+#[test]
+fn qos_presets_match_rmw() {
+  let is_reliable =
+    |qos: &QosPolicies| matches!(qos.reliability(), Some(Reliability::Reliable { .. }));
+
+  let sensor_data = sensor_data();
+  assert_eq!(sensor_data.reliability(), Some(Reliability::BestEffort));
+  assert_eq!(sensor_data.history(), Some(History::KeepLast { depth: 5 }));
+  assert_eq!(sensor_data.durability(), Some(Durability::Volatile));
+
+  for qos in [parameters(), parameter_events()] {
+    assert!(is_reliable(&qos));
+    assert_eq!(qos.history(), Some(History::KeepLast { depth: 1000 }));
+    assert_eq!(qos.durability(), Some(Durability::Volatile));
+  }
+
+  let services = services_default();
+  assert!(is_reliable(&services));
+  assert_eq!(services.history(), Some(History::KeepLast { depth: 10 }));
+  assert_eq!(services.durability(), Some(Durability::Volatile));
+
+  let system_default = system_default();
+  assert_eq!(system_default.reliability(), None);
+  assert_eq!(system_default.durability(), None);
+}
+
+#[test]
+fn qos_request_offered_rules() {
+  let best_effort = sensor_data();
+  let reliable = services_default();
+
+  assert_eq!(incompatibilities(&best_effort, &reliable).len(), 1);
+  assert!(incompatibilities(&reliable, &best_effort).is_empty());
+  assert!(incompatibilities(&reliable, &reliable).is_empty());
+  assert!(incompatibilities(&system_default(), &reliable).is_empty()); // Reliable by default
+  assert!(incompatibilities(&best_effort, &system_default()).is_empty()); // BestEffort by default
+
+  let transient_local = QosPolicyBuilder::new()
+    .durability(Durability::TransientLocal)
+    .build();
+  assert_eq!(incompatibilities(&best_effort, &transient_local).len(), 1);
+  assert!(incompatibilities(&transient_local, &best_effort).is_empty());
+  // Volatile by default
+  assert_eq!(
+    incompatibilities(&system_default(), &transient_local).len(),
+    1
+  );
+  assert!(incompatibilities(&transient_local, &system_default()).is_empty());
+
+  let deadline = |ms| {
+    QosPolicyBuilder::new()
+      .deadline(Deadline(Duration::from_millis(ms)))
+      .build()
+  };
+  assert!(incompatibilities(&deadline(100), &deadline(200)).is_empty());
+  assert_eq!(incompatibilities(&deadline(200), &deadline(100)).len(), 1);
+  // No deadline by default
+  assert_eq!(
+    incompatibilities(&system_default(), &deadline(100)).len(),
+    1
+  );
+  assert!(incompatibilities(&deadline(100), &system_default()).is_empty());
+}
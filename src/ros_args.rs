@@ -0,0 +1,250 @@
+//! Parsing of ROS 2 command line arguments, i.e. the part after `--ros-args`.
+//!
+//! See [ROS 2 command line arguments](https://design.ros2.org/articles/ros_command_line_arguments.html).
+//! Only name remapping (`-r` / `--remap`) is implemented.
+
+#[allow(unused_imports)]
+use log::{debug, error, info, trace, warn};
+
+use crate::names::{Name, NodeName};
+
+// A name remapping rule, e.g. `-r chatter:=/my_chatter`
+#[derive(Debug, Clone, PartialEq)]
+enum Remap {
+  NodeName(String),
+  Namespace(String),
+  Topic { from: Name, to: Name },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct RemapRule {
+  node: Option<String>, // Rule applies only to this Node, if given.
+  remap: Remap,
+}
+
+/// Parsed ROS 2 arguments.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub(crate) struct RosArgs {
+  rules: Vec<RemapRule>,
+}
+
+// These flags take an argument, but we do not implement them.
+const UNSUPPORTED_FLAGS_WITH_ARG: &[&str] = &[
+  "-p",
+  "--param",
+  "--params-file",
+  "-e",
+  "--enclave",
+  "--log-level",
+  "--log-config-file",
+];
+const UNSUPPORTED_FLAGS: &[&str] = &[
+  "--enable-rosout-logs",
+  "--disable-rosout-logs",
+  "--enable-stdout-logs",
+  "--disable-stdout-logs",
+  "--enable-external-lib-logs",
+  "--disable-external-lib-logs",
+];
+
+impl RosArgs {
+  /// Parse a full command line. Only arguments between `--ros-args` and `--`
+  /// (or the end) are considered. There may be several such sections.
+  pub fn parse(args: &[String]) -> Result<RosArgs, String> {
+    let mut rules = Vec::new();
+    let mut in_ros_args = false;
+    let mut iter = args.iter();
+
+    while let Some(arg) = iter.next() {
+      let arg = arg.as_str();
+      if arg == "--ros-args" {
+        in_ros_args = true;
+      } else if !in_ros_args {
+        // not for us
+      } else if arg == "--" {
+        in_ros_args = false;
+      } else if arg == "-r" || arg == "--remap" {
+        let rule = iter
+          .next()
+          .ok_or_else(|| format!("Missing remap rule after {arg}"))?;
+        rules.push(parse_remap_rule(rule)?);
+      } else if UNSUPPORTED_FLAGS_WITH_ARG.contains(&arg) {
+        let value = iter
+          .next()
+          .ok_or_else(|| format!("Missing value after {arg}"))?;
+        warn!("ROS argument {arg} {value} is not supported. Ignored.");
+      } else if UNSUPPORTED_FLAGS.contains(&arg) {
+        warn!("ROS argument {arg} is not supported. Ignored.");
+      } else {
+        return Err(format!("Unknown ROS argument \"{arg}\""));
+      }
+    }
+
+    Ok(RosArgs { rules })
+  }
+
+  /// Append rules from `other`. These have lower precedence than the existing
+  /// rules.
+  pub fn extend(&mut self, other: RosArgs) {
+    self.rules.extend(other.rules)
+  }
+
+  /// Keep only rules that apply to a Node with the given (original) base name.
+  pub fn for_node(self, node_base_name: &str) -> RosArgs {
+    RosArgs {
+      rules: self
+        .rules
+        .into_iter()
+        .filter(|r| r.node.as_ref().map_or(true, |n| n == node_base_name))
+        .collect(),
+    }
+  }
+
+  /// Apply `__node:=` and `__ns:=` rules. The first matching rule is used.
+  pub fn remap_node_name(&self, node_name: NodeName) -> Result<NodeName, String> {
+    let base_name = self
+      .rules
+      .iter()
+      .find_map(|r| match &r.remap {
+        Remap::NodeName(n) => Some(n.as_str()),
+        _ => None,
+      })
+      .unwrap_or(node_name.base_name());
+    let namespace = self
+      .rules
+      .iter()
+      .find_map(|r| match &r.remap {
+        Remap::Namespace(ns) => Some(ns.as_str()),
+        _ => None,
+      })
+      .unwrap_or(node_name.namespace());
+    NodeName::new(namespace, base_name)
+      .map_err(|e| format!("Remapped node name {namespace}/{base_name} is invalid: {e}"))
+  }
+
+  /// Apply topic remapping rules. The first matching rule is used.
+  pub fn remap_topic(&self, name: &Name, node_name: &NodeName) -> Name {
+    let expanded = expand(name, node_name);
+    for rule in &self.rules {
+      if let Remap::Topic { from, to } = &rule.remap {
+        if expand(from, node_name) == expanded {
+          debug!("Remapping topic {name} to {to}");
+          return to.clone();
+        }
+      }
+    }
+    name.clone()
+  }
+}
+
+// Fully qualified name, so that names given in different forms can be
+// compared.
+fn expand(name: &Name, node_name: &NodeName) -> String {
+  name.to_dds_name("", node_name, "")
+}
+
+fn parse_remap_rule(rule: &str) -> Result<RemapRule, String> {
+  let (lhs, to) = rule
+    .split_once(":=")
+    .ok_or_else(|| format!("Remap rule \"{rule}\" does not contain \":=\""))?;
+  let (node, from) = match lhs.split_once(':') {
+    Some((node, from)) => (Some(node.to_string()), from),
+    None => (None, lhs),
+  };
+  if from.is_empty() || to.is_empty() {
+    return Err(format!("Remap rule \"{rule}\" has an empty side"));
+  }
+
+  let remap = match from {
+    "__node" | "__name" => {
+      NodeName::new("", to).map_err(|e| format!("Bad node name in remap rule \"{rule}\": {e}"))?;
+      Remap::NodeName(to.to_string())
+    }
+    "__ns" => {
+      if !to.starts_with('/') {
+        return Err(format!(
+          "Namespace in remap rule \"{rule}\" must be absolute"
+        ));
+      }
+      // The root namespace is represented as "" in NodeName.
+      let ns = if to == "/" { "" } else { to };
+      NodeName::new(ns, "n").map_err(|e| format!("Bad namespace in remap rule \"{rule}\": {e}"))?;
+      Remap::Namespace(ns.to_string())
+    }
+    _ => Remap::Topic {
+      from: Name::parse(from).map_err(|e| format!("Bad name in remap rule \"{rule}\": {e}"))?,
+      to: Name::parse(to).map_err(|e| format!("Bad name in remap rule \"{rule}\": {e}"))?,
+    },
+  };
+
+  Ok(RemapRule { node, remap })
+}
+
+// -------------------------------------------------------------------------------------
+
+#[cfg(test)]
+fn to_args(args: &[&str]) -> Vec<String> {
+  args.iter().map(|s| s.to_string()).collect()
+}
+
+#[test]
+fn ros_args_remap_namespace_and_topic() {
+  let args = RosArgs::parse(&to_args(&[
+    "my_program",
+    "--verbose",
+    "--ros-args",
+    "-r",
+    "__ns:=/foo",
+    "--remap",
+    "chatter:=/my_chatter",
+    "--",
+    "-r",
+  ]))
+  .unwrap()
+  .for_node("talker");
+
+  let node_name = args
+    .remap_node_name(NodeName::new("", "talker").unwrap())
+    .unwrap();
+  assert_eq!(node_name, NodeName::new("/foo", "talker").unwrap());
+
+  let topic = args.remap_topic(&Name::parse("chatter").unwrap(), &node_name);
+  assert_eq!(topic, Name::parse("/my_chatter").unwrap());
+
+  // "/foo/chatter" is the same as "chatter" in namespace "/foo"
+  let topic = args.remap_topic(&Name::parse("/foo/chatter").unwrap(), &node_name);
+  assert_eq!(topic, Name::parse("/my_chatter").unwrap());
+
+  let other = Name::parse("other").unwrap();
+  assert_eq!(args.remap_topic(&other, &node_name), other);
+}
+
+#[test]
+fn ros_args_node_specific_rules() {
+  let args = RosArgs::parse(&to_args(&[
+    "--ros-args",
+    "-r",
+    "talker:__node:=speaker",
+    "-r",
+    "listener:__node:=hearer",
+  ]))
+  .unwrap()
+  .for_node("talker");
+
+  let node_name = args
+    .remap_node_name(NodeName::new("", "talker").unwrap())
+    .unwrap();
+  assert_eq!(node_name.base_name(), "speaker");
+}
+
+#[test]
+fn ros_args_errors() {
+  assert!(RosArgs::parse(&to_args(&["--ros-args", "chatter:=foo"])).is_err());
+  assert!(RosArgs::parse(&to_args(&["--ros-args", "-r"])).is_err());
+  assert!(RosArgs::parse(&to_args(&["--ros-args", "-r", "chatter"])).is_err());
+  assert!(RosArgs::parse(&to_args(&["--ros-args", "-r", "chatter:="])).is_err());
+  assert!(RosArgs::parse(&to_args(&["--ros-args", "-r", "__ns:=foo"])).is_err());
+  assert!(RosArgs::parse(&to_args(&["--ros-args", "-r", "a//b:=c"])).is_err());
+  // Not within --ros-args, so not our business
+  assert!(RosArgs::parse(&to_args(&["-r", "chatter"])).is_ok());
+}
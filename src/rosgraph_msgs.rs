@@ -0,0 +1,14 @@
+//! Message types from [rosgraph_msgs](https://index.ros.org/p/rosgraph_msgs/)
+
+use serde::{Deserialize, Serialize};
+
+use crate::{builtin_interfaces, message::Message};
+
+/// From [Clock](https://github.com/ros2/rcl_interfaces/blob/rolling/rosgraph_msgs/msg/Clock.msg)
+///
+/// Published on `/clock` by simulators and bag players.
+#[derive(Clone, Serialize, Deserialize, Debug)]
+pub struct Clock {
+  pub clock: builtin_interfaces::Time,
+}
+impl Message for Clock {}
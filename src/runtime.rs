@@ -0,0 +1,39 @@
+//! Async runtime dependent parts, i.e. timers and blocking on a future.
+//!
+//! Everything else in this crate is runtime-neutral (`futures` and
+//! `async-channel`). The runtime is chosen with feature `rt-smol` (default)
+//! or `rt-tokio`.
+
+use std::time::Duration;
+
+#[cfg(not(any(feature = "rt-smol", feature = "rt-tokio")))]
+compile_error!("Either feature \"rt-smol\" or \"rt-tokio\" must be enabled.");
+
+/// Wait until `duration` has elapsed.
+#[cfg(feature = "rt-tokio")]
+pub(crate) async fn sleep(duration: Duration) {
+  tokio::time::sleep(duration).await
+}
+
+/// Wait until `duration` has elapsed.
+#[cfg(all(feature = "rt-smol", not(feature = "rt-tokio")))]
+pub(crate) async fn sleep(duration: Duration) {
+  async_io::Timer::after(duration).await;
+}
+
+/// Run `future` to completion, blocking the current thread.
+#[cfg(feature = "rt-tokio")]
+pub(crate) fn block_on<F: std::future::Future>(future: F) -> F::Output {
+  // Panics if called from within a Tokio runtime, like Runtime::block_on.
+  tokio::runtime::Builder::new_current_thread()
+    .enable_time()
+    .build()
+    .expect("Cannot create Tokio runtime")
+    .block_on(future)
+}
+
+/// Run `future` to completion, blocking the current thread.
+#[cfg(all(feature = "rt-smol", not(feature = "rt-tokio")))]
+pub(crate) fn block_on<F: std::future::Future>(future: F) -> F::Output {
+  async_io::block_on(future)
+}
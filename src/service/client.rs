@@ -1,16 +1,16 @@
-use std::{io, sync::atomic};
+use std::{io, sync::atomic, time::Duration};
 
 use mio::{Evented, Poll, PollOpt, Ready, Token};
 #[allow(unused_imports)]
 use log::{debug, error, info, warn};
-use futures::{join, pin_mut, StreamExt};
+use futures::{pin_mut, stream::FusedStream, FutureExt, Stream, StreamExt};
 use rustdds::{
   dds::{CreateResult, ReadError, ReadResult, WriteError, WriteResult},
   rpc::*,
   *,
 };
 
-use crate::{message::Message, message_info::MessageInfo, node::Node, service::*};
+use crate::{message::Message, message_info::MessageInfo, node::Node, runtime, service::*};
 
 /// Client end of a ROS2 Service
 pub struct Client<S>
@@ -195,6 +195,25 @@ where
     } // loop
   }
 
+  /// Returns a never-ending stream of (request_id, response), for any
+  /// responses, not only to a specific request. Compare the request_id to the
+  /// ones returned when sending requests.
+  ///
+  /// The stream is fused, so it can be used with `select!`.
+  pub fn response_stream(
+    &self,
+  ) -> impl Stream<Item = ReadResult<(RmwRequestId, S::Response)>> + FusedStream + '_ {
+    Box::pin(self.response_receiver.as_async_stream()).map(move |dcc_r| match dcc_r {
+      Err(e) => Err(e),
+      Ok(dcc) => {
+        let mi = MessageInfo::from(&dcc);
+        dcc
+          .into_value()
+          .unwrap(self.service_mapping, mi, self.client_guid)
+      }
+    })
+  }
+
   pub async fn async_call_service(
     &self,
     request: S::Request,
@@ -206,16 +225,82 @@ where
       .map_err(CallServiceError::from)
   }
 
-  /// Wait for a Server to be connected to the Request and Response topics.
+  /// Call a Service, but give up if no response is received within `timeout`.
+  ///
+  /// Returns `Ok(None)` if the timeout elapsed. A late response to the request
+  /// is not remembered: it is ignored by later calls, because they wait for
+  /// their own `RmwRequestId`, but [`receive_response`](Self::receive_response)
+  /// returns it like any other response.
+  pub async fn async_call_service_timeout(
+    &self,
+    request: S::Request,
+    timeout: Duration,
+  ) -> Result<Option<S::Response>, CallServiceError<()>> {
+    let req_id = self.async_send_request(request).await?;
+    let response_fut = self.async_receive_response(req_id).fuse();
+    pin_mut!(response_fut);
+    let timeout_fut = runtime::sleep(timeout).fuse();
+    pin_mut!(timeout_fut);
+    let response = futures::select! {
+      response = response_fut => Some(response),
+      _ = timeout_fut => None,
+    };
+    match response {
+      Some(response) => response.map(Some).map_err(CallServiceError::from),
+      None => {
+        debug!("Service call {req_id:?} timed out after {timeout:?}");
+        Ok(None)
+      }
+    }
+  }
+
+  /// Is a Server connected to the Request and Response topics?
   ///
   /// This does not distinguish between diagnostinc tools and actual servers.
   /// It is enough that someone has subscribed the Requests, and someone is
   /// a publisher for Responses.
+  ///
+  /// Connections are tracked by the [`Spinner`](crate::Spinner) of `my_node`,
+  /// which must be the Node that created this Client.
+  pub fn service_is_ready(&self, my_node: &Node) -> bool {
+    my_node.has_remote_reader(self.request_sender.guid())
+      && my_node.has_remote_writer(self.response_receiver.guid())
+  }
+
+  /// Wait for a Server to be connected to the Request and Response topics.
+  ///
+  /// Completes only when both topics are connected at the same time, see
+  /// [`Self::service_is_ready`]. If a Server is lost during the wait, waiting
+  /// continues until some Server is available again.
+  ///
+  /// This is cancellation-safe: dropping the future before completion has no
+  /// side effects.
   pub async fn wait_for_service(&self, my_node: &Node) {
-    join!(
-      my_node.wait_for_reader(self.request_sender.guid()),
-      my_node.wait_for_writer(self.response_receiver.guid())
-    );
+    // Subscribe to discovery events before checking the current state, so
+    // that no change is missed in between.
+    let status_receiver = my_node.status_receiver();
+    pin_mut!(status_receiver);
+
+    while !self.service_is_ready(my_node) {
+      // Any event may change readiness, so just check again.
+      if status_receiver.next().await.is_none() {
+        warn!("Node status events ended while waiting for service.");
+        return;
+      }
+    }
+  }
+
+  /// Same as [`Self::wait_for_service`].
+  pub async fn async_wait_for_service(&self, my_node: &Node) {
+    self.wait_for_service(my_node).await
+  }
+
+  /// Blocking version of [`Self::wait_for_service`].
+  ///
+  /// Blocks the calling thread, so the [`Spinner`](crate::Spinner) of
+  /// `my_node` must be running in some other thread.
+  pub fn wait_for_service_blocking(&self, my_node: &Node) {
+    futures::executor::block_on(self.wait_for_service(my_node))
   }
 
   fn increment_sequence_number(&self) {
@@ -272,3 +357,121 @@ where
     self.response_receiver.deregister(poll)
   }
 }
+
+// -------------------------------------------------------------------------------------
+
+// The client and server are in separate DomainParticipants.
+#[test]
+#[ignore]
+fn wait_for_service_waits_for_server() {
+  use crate::{
+    test_util::{reliable_qos, test_node},
+    Name, ServiceTypeName,
+  };
+
+  let service_name = Name::new("/wait_test", "add").unwrap();
+  let type_name = ServiceTypeName::new("example_interfaces", "AddTwoInts");
+  let qos = reliable_qos();
+
+  let mut client_node = test_node("/wait_test", "client_node");
+  let spinner = client_node.spinner().unwrap();
+  std::thread::spawn(move || smol::block_on(spinner.spin()));
+  let client = client_node
+    .create_client::<AService<i64, i64>>(
+      ServiceMapping::Enhanced,
+      &service_name,
+      &type_name,
+      qos.clone(),
+      qos.clone(),
+    )
+    .unwrap();
+
+  let wait_with_timeout = |secs| {
+    smol::future::or(
+      client.wait_for_service(&client_node).map(|_| true),
+      FutureExt::map(async_io::Timer::after(Duration::from_secs(secs)), |_| false),
+    )
+  };
+
+  // No server yet
+  assert!(!smol::block_on(wait_with_timeout(2)));
+  assert!(!client.service_is_ready(&client_node));
+
+  let mut server_node = test_node("/wait_test", "server_node");
+  let _server = server_node
+    .create_server::<AService<i64, i64>>(
+      ServiceMapping::Enhanced,
+      &service_name,
+      &type_name,
+      qos.clone(),
+      qos,
+    )
+    .unwrap();
+
+  assert!(smol::block_on(wait_with_timeout(20)));
+  assert!(client.service_is_ready(&client_node));
+}
+
+#[test]
+#[ignore]
+fn call_service_timeout_ignores_late_response() {
+  use crate::{
+    test_util::{reliable_qos, test_node},
+    Name, ServiceTypeName,
+  };
+
+  let service_name = Name::new("/timeout_test", "double").unwrap();
+  let type_name = ServiceTypeName::new("example_interfaces", "Double");
+  let qos = reliable_qos();
+
+  // Server that responds to the first request only after receiving the second.
+  let (server_name, server_type, server_qos) =
+    (service_name.clone(), type_name.clone(), qos.clone());
+  std::thread::spawn(move || {
+    let mut node = test_node("/timeout_test", "server_node");
+    let server = node
+      .create_server::<AService<i64, i64>>(
+        ServiceMapping::Enhanced,
+        &server_name,
+        &server_type,
+        server_qos.clone(),
+        server_qos,
+      )
+      .unwrap();
+    smol::block_on(async {
+      let (first_id, _) = server.async_receive_request().await.unwrap();
+      let (second_id, x) = server.async_receive_request().await.unwrap();
+      server.async_send_response(first_id, -1).await.unwrap();
+      server.async_send_response(second_id, 2 * x).await.unwrap();
+      // Keep the Server around until the client has the response.
+      async_io::Timer::after(Duration::from_secs(5)).await;
+    });
+  });
+
+  let mut node = test_node("/timeout_test", "client_node");
+  let spinner = node.spinner().unwrap();
+  std::thread::spawn(move || smol::block_on(spinner.spin()));
+  let client = node
+    .create_client::<AService<i64, i64>>(
+      ServiceMapping::Enhanced,
+      &service_name,
+      &type_name,
+      qos.clone(),
+      qos,
+    )
+    .unwrap();
+
+  smol::block_on(async {
+    client.wait_for_service(&node).await;
+    let first = client
+      .async_call_service_timeout(1, Duration::from_secs(1))
+      .await
+      .unwrap();
+    assert_eq!(first, None);
+    let second = client
+      .async_call_service_timeout(21, Duration::from_secs(10))
+      .await
+      .unwrap();
+    assert_eq!(second, Some(42));
+  });
+}
@@ -60,6 +60,11 @@ where
     })
   }
 
+  // GUID of the DataReader receiving requests
+  pub(crate) fn request_reader_guid(&self) -> GUID {
+    self.request_receiver.guid()
+  }
+
   /// Receive a request from Client.
   /// Returns `Ok(None)` if no new requests have arrived.
   pub fn receive_request(&self) -> ReadResult<Option<(RmwRequestId, S::Request)>> {
@@ -109,6 +114,16 @@ where
   /// The request_id must be sent back with the response to identify which
   /// request and response belong together.
   pub async fn async_receive_request(&self) -> ReadResult<(RmwRequestId, S::Request)> {
+    let (ri, req, _mi) = self.async_receive_request_with_info().await?;
+    Ok((ri, req))
+  }
+
+  // Same as async_receive_request, but also gives the metadata of the request
+  // sample. Its writer GUID is the actual DataWriter of the Client, whereas
+  // the RmwRequestId is whatever the Client chose to put there.
+  pub(crate) async fn async_receive_request_with_info(
+    &self,
+  ) -> ReadResult<(RmwRequestId, S::Request, MessageInfo)> {
     let dcc_stream = self.request_receiver.as_async_stream();
     pin_mut!(dcc_stream);
 
@@ -118,7 +133,7 @@ where
         let mi = MessageInfo::from(&dcc);
         let req_wrapper = dcc.into_value();
         let (ri, req) = req_wrapper.unwrap(self.service_mapping, &mi)?;
-        Ok((ri, req))
+        Ok((ri, req, mi))
       }
       // This should never occur, because topic do not "end".
       None => read_error_internal!("SimpleDataReader value stream unexpectedly ended!"),
@@ -128,7 +143,9 @@ where
   /// Returns a never-ending stream of (request_id, request)
   /// The request_id must be sent back with the response to identify which
   /// request and response belong together.
-  pub fn receive_request_stream(
+  ///
+  /// The stream is fused, so it can be used with `select!`.
+  pub fn request_stream(
     &self,
   ) -> impl Stream<Item = ReadResult<(RmwRequestId, S::Request)>> + FusedStream + '_ {
     Box::pin(self.request_receiver.as_async_stream()).then(
@@ -145,6 +162,13 @@ where
     )
   }
 
+  /// Same as [`Self::request_stream`].
+  pub fn receive_request_stream(
+    &self,
+  ) -> impl Stream<Item = ReadResult<(RmwRequestId, S::Request)>> + FusedStream + '_ {
+    self.request_stream()
+  }
+
   /// Asynchronous response sending
   pub async fn async_send_response(
     &self,
@@ -198,3 +222,56 @@ where
     self.request_receiver.deregister(poll)
   }
 }
+
+// -------------------------------------------------------------------------------------
+
+#[test]
+#[ignore]
+fn request_stream_serves_many_requests() {
+  use crate::{
+    test_util::{reliable_qos, test_node},
+    AService, Name, ServiceTypeName,
+  };
+
+  let service_name = Name::new("/stream_test", "negate").unwrap();
+  let type_name = ServiceTypeName::new("stream_test", "Negate");
+  let qos = reliable_qos();
+
+  let mut node = test_node("/stream_test", "node");
+  let spinner = node.spinner().unwrap();
+  std::thread::spawn(move || smol::block_on(spinner.spin()));
+  let server = node
+    .create_server::<AService<i64, i64>>(
+      ServiceMapping::Enhanced,
+      &service_name,
+      &type_name,
+      qos.clone(),
+      qos.clone(),
+    )
+    .unwrap();
+  let client = node
+    .create_client::<AService<i64, i64>>(
+      ServiceMapping::Enhanced,
+      &service_name,
+      &type_name,
+      qos.clone(),
+      qos,
+    )
+    .unwrap();
+
+  let serve = async {
+    let request_stream = server.request_stream();
+    pin_mut!(request_stream);
+    for _ in 0..3 {
+      let (req_id, x) = request_stream.next().await.unwrap().unwrap();
+      server.async_send_response(req_id, -x).await.unwrap();
+    }
+  };
+  let call = async {
+    client.wait_for_service(&node).await;
+    for x in 1..=3 {
+      assert_eq!(client.async_call_service(x).await.unwrap(), -x);
+    }
+  };
+  smol::block_on(futures::future::join(serve, call));
+}
@@ -0,0 +1,155 @@
+//! Fixtures shared by tests
+//!
+//! Tests marked `#[ignore]` use DDS Discovery and networking, so they need a
+//! working (multicast) network interface. Run them with
+//! `cargo test -- --ignored`.
+
+use std::sync::Mutex;
+
+use serde::{Deserialize, Serialize};
+use rustdds::*;
+
+use crate::{
+  action::{
+    Action, ActionClient, ActionClientQosPolicies, ActionServerQosPolicies, AsyncActionServer,
+  },
+  ActionTypeName, Context, MessageTypeName, Name, Node, NodeName, NodeOptions, ServiceMapping,
+};
+
+/// Reliable, and keeps the last 10 samples.
+pub(crate) fn reliable_qos() -> QosPolicies {
+  QosPolicyBuilder::new()
+    .reliability(policy::Reliability::Reliable {
+      max_blocking_time: rustdds::Duration::from_millis(100),
+    })
+    .history(policy::History::KeepLast { depth: 10 })
+    .build()
+}
+
+/// A Node `namespace/name` in a new Context.
+pub(crate) fn test_node(namespace: &str, name: &str) -> Node {
+  Context::new()
+    .unwrap()
+    .new_node(NodeName::new(namespace, name).unwrap(), NodeOptions::new())
+    .unwrap()
+}
+
+/// Topic `name` of type `std_msgs/Int32` in the namespace of `node`.
+pub(crate) fn int32_topic(node: &mut Node, name: &str, qos: &QosPolicies) -> Topic {
+  let name = Name::new(node.namespace(), name).unwrap();
+  node
+    .create_topic(&name, MessageTypeName::new("std_msgs", "Int32"), qos)
+    .unwrap()
+}
+
+/// Keyed message type. Each sensor is an instance.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub(crate) struct Reading {
+  pub sensor: i32,
+  pub value: i32,
+}
+
+impl Keyed for Reading {
+  type K = i32;
+  fn key(&self) -> i32 {
+    self.sensor
+  }
+}
+
+/// Goal, result and feedback are all numbers.
+pub(crate) type CountAction = Action<i32, i32, i32>;
+
+/// Server and client of [`CountAction`] `count` in the namespace of `node`,
+/// using [`reliable_qos`].
+pub(crate) fn count_action(
+  node: &mut Node,
+) -> (AsyncActionServer<CountAction>, ActionClient<CountAction>) {
+  let qos = reliable_qos();
+  let action_name = Name::new(node.namespace(), "count").unwrap();
+  let action_type = ActionTypeName::new("test_interfaces", "Count");
+  let server = node
+    .create_action_server::<CountAction>(
+      ServiceMapping::Enhanced,
+      &action_name,
+      &action_type,
+      ActionServerQosPolicies {
+        goal_service: qos.clone(),
+        result_service: qos.clone(),
+        cancel_service: qos.clone(),
+        feedback_publisher: qos.clone(),
+        status_publisher: qos.clone(),
+      },
+    )
+    .unwrap();
+  let client = node
+    .create_action_client::<CountAction>(
+      ServiceMapping::Enhanced,
+      &action_name,
+      &action_type,
+      ActionClientQosPolicies {
+        goal_service: qos.clone(),
+        result_service: qos.clone(),
+        cancel_service: qos.clone(),
+        feedback_subscription: qos.clone(),
+        status_subscription: qos,
+      },
+    )
+    .unwrap();
+  (AsyncActionServer::new(server), client)
+}
+
+/// Records of the `log` crate with a target starting with `test_`, captured
+/// by [`test_logger`]
+pub(crate) struct TestLogger(Mutex<Vec<(String, ::log::Level, String)>>);
+
+impl TestLogger {
+  /// Remove and return the captured (level, message) records of `target`.
+  /// Each test should use its own targets, because tests run concurrently.
+  pub(crate) fn take(&self, target: &str) -> Vec<(::log::Level, String)> {
+    let mut records = self.0.lock().unwrap();
+    let (taken, kept) = records.drain(..).partition(|(t, _, _)| t == target);
+    *records = kept;
+    taken
+      .into_iter()
+      .map(|(_, level, msg)| (level, msg))
+      .collect()
+  }
+}
+
+impl ::log::Log for TestLogger {
+  fn enabled(&self, _: &::log::Metadata) -> bool {
+    true
+  }
+  fn log(&self, record: &::log::Record) {
+    if !record.target().starts_with("test_") {
+      return; // not from a test
+    }
+    self.0.lock().unwrap().push((
+      record.target().to_string(),
+      record.level(),
+      record.args().to_string(),
+    ));
+  }
+  fn flush(&self) {}
+}
+
+/// The logger that captures `log` records for tests, installing it on first
+/// call. Returns `None`, if some other logger was installed first, so that
+/// nothing can be captured.
+pub(crate) fn test_logger() -> Option<&'static TestLogger> {
+  lazy_static! {
+    static ref LOGGER: TestLogger = TestLogger(Mutex::new(Vec::new()));
+    static ref INSTALLED: bool = {
+      let installed = ::log::set_logger(&*LOGGER).is_ok();
+      if installed {
+        ::log::set_max_level(::log::LevelFilter::Trace);
+      }
+      installed
+    };
+  }
+  if *INSTALLED {
+    Some(&*LOGGER)
+  } else {
+    None
+  }
+}
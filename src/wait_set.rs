@@ -0,0 +1,272 @@
+//! [`WaitSet`] for waiting on several Subscriptions, Clients and Servers at
+//! once, in the style of the rcl WaitSet.
+
+use std::{any::Any, collections::BTreeMap, marker::PhantomData, pin::Pin, task::Poll};
+
+use futures::{
+  future::poll_fn,
+  stream::{FusedStream, Stream, StreamExt},
+  FutureExt,
+};
+use serde::de::DeserializeOwned;
+use rustdds::{dds::ReadResult, rpc::RmwRequestId};
+
+use crate::{
+  message_info::MessageInfo,
+  pubsub::Subscription,
+  service::{Client, Server, Service},
+};
+
+/// Identifies an entity added to a [`WaitSet`]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct EntityId(usize);
+
+/// Returned when adding an entity to a [`WaitSet`]. Used to take the data of
+/// the entity from the WaitSet. `T` is the type of the taken items.
+#[derive(Debug)]
+pub struct EntityKey<T> {
+  id: EntityId,
+  item: PhantomData<fn() -> T>,
+}
+
+impl<T> EntityKey<T> {
+  pub fn id(&self) -> EntityId {
+    self.id
+  }
+}
+
+/// An entity that has data ready, as reported by [`WaitSet::wait`]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum ReadyEntity {
+  /// A Subscription has a message.
+  Subscription(EntityId),
+  /// A Client has a response.
+  Client(EntityId),
+  /// A Server has a request.
+  Server(EntityId),
+}
+
+impl ReadyEntity {
+  pub fn id(&self) -> EntityId {
+    match self {
+      ReadyEntity::Subscription(id) | ReadyEntity::Client(id) | ReadyEntity::Server(id) => *id,
+    }
+  }
+}
+
+type ItemStream<'a> = Pin<Box<dyn FusedStream<Item = Box<dyn Any>> + 'a>>;
+
+struct Entry<'a> {
+  ready: fn(EntityId) -> ReadyEntity,
+  stream: ItemStream<'a>,
+  // An item received from the stream, but not yet taken
+  pending: Option<Box<dyn Any>>,
+}
+
+/// Waits until any of several [`Subscription`]s, [`Client`]s or [`Server`]s
+/// has data, for writing a single-threaded control loop like with the rcl
+/// WaitSet.
+///
+/// Waiting is level-triggered: an entity is reported by every
+/// [`wait`](Self::wait) for as long as it has data. The WaitSet reads the
+/// async stream of each entity, and holds on to the first item it gets, so
+/// take the data with [`take`](Self::take), not from the entity itself.
+///
+/// Entities can be added and removed between waits.
+///
+/// ```no_run
+/// # use ros2_client::*;
+/// # async fn control_loop(a: Subscription<i32>, b: Subscription<i32>) {
+/// let mut wait_set = WaitSet::new();
+/// let key_a = wait_set.add_subscription(&a);
+/// let key_b = wait_set.add_subscription(&b);
+/// loop {
+///   for ready in wait_set.wait().await {
+///     if ready.id() == key_a.id() {
+///       let message = wait_set.take(&key_a);
+///       // ...
+///     } else if ready.id() == key_b.id() {
+///       let message = wait_set.take(&key_b);
+///       // ...
+///     }
+///   }
+/// }
+/// # }
+/// ```
+pub struct WaitSet<'a> {
+  entries: BTreeMap<EntityId, Entry<'a>>,
+  next_id: usize,
+}
+
+impl<'a> WaitSet<'a> {
+  pub fn new() -> WaitSet<'a> {
+    WaitSet {
+      entries: BTreeMap::new(),
+      next_id: 0,
+    }
+  }
+
+  /// Add a Subscription to wait on. Its messages are taken as
+  /// `ReadResult<(M, MessageInfo)>`.
+  pub fn add_subscription<M>(
+    &mut self,
+    subscription: &'a Subscription<M>,
+  ) -> EntityKey<ReadResult<(M, MessageInfo)>>
+  where
+    M: 'static + DeserializeOwned,
+  {
+    self.add(ReadyEntity::Subscription, subscription.async_stream())
+  }
+
+  /// Add a Client to wait on. Its responses are taken as
+  /// `ReadResult<(RmwRequestId, S::Response)>`.
+  pub fn add_client<S>(
+    &mut self,
+    client: &'a Client<S>,
+  ) -> EntityKey<ReadResult<(RmwRequestId, S::Response)>>
+  where
+    S: 'static + Service,
+  {
+    self.add(ReadyEntity::Client, client.response_stream())
+  }
+
+  /// Add a Server to wait on. Its requests are taken as
+  /// `ReadResult<(RmwRequestId, S::Request)>`.
+  pub fn add_server<S>(
+    &mut self,
+    server: &'a Server<S>,
+  ) -> EntityKey<ReadResult<(RmwRequestId, S::Request)>>
+  where
+    S: 'static + Service,
+  {
+    self.add(ReadyEntity::Server, server.request_stream())
+  }
+
+  fn add<T: 'static>(
+    &mut self,
+    ready: fn(EntityId) -> ReadyEntity,
+    stream: impl Stream<Item = T> + FusedStream + 'a,
+  ) -> EntityKey<T> {
+    let id = EntityId(self.next_id);
+    self.next_id += 1;
+    let stream = stream.map(|item| Box::new(item) as Box<dyn Any>);
+    self.entries.insert(
+      id,
+      Entry {
+        ready,
+        stream: Box::pin(stream),
+        pending: None,
+      },
+    );
+    EntityKey {
+      id,
+      item: PhantomData,
+    }
+  }
+
+  /// Remove an entity. Data that the WaitSet already received from it is
+  /// dropped. Does nothing, if the entity is not in this WaitSet.
+  pub fn remove(&mut self, id: EntityId) {
+    self.entries.remove(&id);
+  }
+
+  /// Wait until some entities have data ready.
+  ///
+  /// Returns all the entities that have data, in the order they were added.
+  /// Waits forever, if no entity ever gets any data.
+  pub async fn wait(&mut self) -> Vec<ReadyEntity> {
+    poll_fn(|cx| {
+      let mut ready = Vec::new();
+      for (id, entry) in self.entries.iter_mut() {
+        if entry.pending.is_none() && !entry.stream.is_terminated() {
+          if let Poll::Ready(item) = entry.stream.as_mut().poll_next(cx) {
+            entry.pending = item;
+          }
+        }
+        if entry.pending.is_some() {
+          ready.push((entry.ready)(*id));
+        }
+      }
+      if ready.is_empty() {
+        Poll::Pending
+      } else {
+        Poll::Ready(ready)
+      }
+    })
+    .await
+  }
+
+  /// Take the next item of an entity without waiting. This is the item
+  /// reported by [`wait`](Self::wait), if any, or else the next one that has
+  /// arrived since.
+  ///
+  /// Returns `None`, if the entity has no data, or if it is not in this
+  /// WaitSet.
+  pub fn take<T: 'static>(&mut self, key: &EntityKey<T>) -> Option<T> {
+    let entry = self.entries.get_mut(&key.id)?;
+    let item = match entry.pending.take() {
+      Some(item) => item,
+      None => entry.stream.next().now_or_never().flatten()?,
+    };
+    // A key from another WaitSet may have the same id, but another type.
+    item.downcast().ok().map(|item| *item)
+  }
+}
+
+impl<'a> Default for WaitSet<'a> {
+  fn default() -> Self {
+    WaitSet::new()
+  }
+}
+
+// -------------------------------------------------------------------------------------
+
+#[test]
+#[ignore]
+fn wait_reports_subscription_with_data() {
+  use std::time::Duration;
+
+  use crate::test_util::{int32_topic, reliable_qos, test_node};
+
+  let mut node = test_node("/wait_set_test", "node");
+  let qos = reliable_qos();
+  let topic_a = int32_topic(&mut node, "a", &qos);
+  let topic_b = int32_topic(&mut node, "b", &qos);
+  let subscription_a = node.create_subscription::<i32>(&topic_a, None).unwrap();
+  let subscription_b = node.create_subscription::<i32>(&topic_b, None).unwrap();
+  let publisher_b = node.create_publisher::<i32>(&topic_b, None).unwrap();
+
+  let mut wait_set = WaitSet::new();
+  let key_a = wait_set.add_subscription(&subscription_a);
+  let key_b = wait_set.add_subscription(&subscription_b);
+  assert_ne!(key_a.id(), key_b.id());
+
+  publisher_b.publish(1).unwrap();
+  publisher_b.publish(2).unwrap();
+  smol::block_on(async {
+    let ready = wait_set.wait().await;
+    assert_eq!(ready, vec![ReadyEntity::Subscription(key_b.id())]);
+
+    // Level-triggered: reported again, until the data is taken
+    let ready = wait_set.wait().await;
+    assert_eq!(ready, vec![ReadyEntity::Subscription(key_b.id())]);
+    let (n, _) = wait_set.take(&key_b).unwrap().unwrap();
+    assert_eq!(n, 1);
+    assert!(wait_set.take(&key_a).is_none());
+
+    // The second message is still there
+    let ready = wait_set.wait().await;
+    assert_eq!(ready, vec![ReadyEntity::Subscription(key_b.id())]);
+    let (n, _) = wait_set.take(&key_b).unwrap().unwrap();
+    assert_eq!(n, 2);
+
+    // Removed entities are no longer reported
+    wait_set.remove(key_b.id());
+    publisher_b.publish(3).unwrap();
+    let timeout = smol::Timer::after(Duration::from_millis(500));
+    futures::select! {
+      ready = wait_set.wait().fuse() => panic!("Unexpected ready entities {ready:?}"),
+      _ = timeout.fuse() => (),
+    }
+  });
+}
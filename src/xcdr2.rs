@@ -0,0 +1,786 @@
+//! Decoder for XCDR2 (Extended CDR encoding version 2) serialized data.
+//!
+//! RustDDS only decodes classic CDR (XCDR1), but publishers on newer ROS 2
+//! distributions may send XCDR2. For the data types used in ROS 2 the
+//! differences are:
+//!
+//! * Maximum alignment is 4 bytes, i.e. 64-bit values are aligned to 4.
+//! * Sequences and arrays of non-primitive types are prefixed with a DHEADER,
+//!   i.e. a 32-bit byte length.
+//! * With delimited encoding (`D_CDR2`, appendable types) each struct is also
+//!   prefixed with a DHEADER.
+//!
+//! CDR is not self-describing, so we must know the element types of sequences
+//! before we can decode them. Serde does not give us that information
+//! directly, so we first "probe" the shape of the type by running its
+//! `Deserialize` implementation against dummy data, and then decode the actual
+//! data guided by the probed shape. The shape is probed only once per type.
+//!
+//! Limitation: probing uses dummy values, i.e. sequences of one element, enum
+//! variant 0 and the string "x". If the `Deserialize` implementation of a
+//! type rejects these, e.g. because it validates values, the shape cannot be
+//! determined, and XCDR2 samples of that type cannot be decoded.
+
+use std::{
+  collections::HashMap,
+  fmt,
+  sync::{Arc, Mutex},
+};
+
+use serde::de::{
+  self, DeserializeOwned, DeserializeSeed, EnumAccess, IntoDeserializer, MapAccess, SeqAccess,
+  VariantAccess, Visitor,
+};
+use rustdds::RepresentationIdentifier;
+
+fn repr_id(b: u8) -> RepresentationIdentifier {
+  RepresentationIdentifier::from_bytes(&[0x00, b]).expect("Two bytes are always enough")
+}
+
+// Encapsulation identifiers from DDS-XTypes v1.3 Section 7.6.3.1.2.
+// RustDDS predefines CDR2_* constants with values from an older
+// specification version, so we define the current ones here.
+lazy_static! {
+  pub(crate) static ref CDR2_BE: RepresentationIdentifier = repr_id(0x06);
+  pub(crate) static ref CDR2_LE: RepresentationIdentifier = repr_id(0x07);
+  pub(crate) static ref D_CDR2_BE: RepresentationIdentifier = repr_id(0x08);
+  pub(crate) static ref D_CDR2_LE: RepresentationIdentifier = repr_id(0x09);
+}
+
+/// How an XCDR2 payload is to be decoded. Derived from the encapsulation
+/// header of a sample.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct Xcdr2Encoding {
+  little_endian: bool,
+  delimited: bool,
+}
+
+impl Xcdr2Encoding {
+  /// Returns `None` if the encoding is not some variant of XCDR2.
+  pub fn from_representation(encoding: RepresentationIdentifier) -> Option<Self> {
+    let (little_endian, delimited) =
+      if encoding == *CDR2_LE || encoding == RepresentationIdentifier::CDR2_LE {
+        (true, false)
+      } else if encoding == *CDR2_BE || encoding == RepresentationIdentifier::CDR2_BE {
+        (false, false)
+      } else if encoding == *D_CDR2_LE {
+        (true, true)
+      } else if encoding == *D_CDR2_BE {
+        (false, true)
+      } else {
+        return None;
+      };
+    Some(Xcdr2Encoding {
+      little_endian,
+      delimited,
+    })
+  }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Error(String);
+
+impl fmt::Display for Error {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    write!(f, "XCDR2 decoding: {}", self.0)
+  }
+}
+
+impl std::error::Error for Error {}
+
+impl de::Error for Error {
+  fn custom<T: fmt::Display>(msg: T) -> Self {
+    Error(msg.to_string())
+  }
+}
+
+type Result<T> = std::result::Result<T, Error>;
+
+lazy_static! {
+  // Probed shapes, or probing errors, keyed by type name. TypeId would need
+  // 'static types, which DeserializeOwned does not guarantee.
+  static ref SHAPES: Mutex<HashMap<&'static str, Result<Arc<Shape>>>> = Mutex::new(HashMap::new());
+}
+
+fn shape_of<T: DeserializeOwned>() -> Result<Arc<Shape>> {
+  let type_name = std::any::type_name::<T>();
+  if let Some(shape) = SHAPES.lock().unwrap().get(type_name) {
+    return shape.clone();
+  }
+  // Probe without holding the lock, in case of nested calls.
+  let mut shape = None;
+  let shape = T::deserialize(ShapeProbe { shape: &mut shape })
+    .map(|_| Arc::new(shape.unwrap_or(Shape::Unit)))
+    .map_err(|e| Error(format!("Cannot determine type shape: {}", e.0)));
+  SHAPES.lock().unwrap().insert(type_name, shape.clone());
+  shape
+}
+
+/// Decode a value from XCDR2 payload. The input must not include the
+/// encapsulation header.
+pub(crate) fn from_bytes<T: DeserializeOwned>(input: &[u8], encoding: Xcdr2Encoding) -> Result<T> {
+  let shape = shape_of::<T>()?;
+
+  let mut decoder = Decoder {
+    input,
+    pos: 0,
+    encoding,
+  };
+  T::deserialize(ShapedDeserializer {
+    decoder: &mut decoder,
+    shape: &shape,
+  })
+}
+
+// ---------------------------------------------------------------------------
+// Shape probing
+
+#[derive(Debug, Clone)]
+enum Shape {
+  Primitive,
+  Unit,
+  String,
+  Enum,
+  Sequence(Box<Shape>),
+  Array(Vec<Shape>),
+  Struct(Vec<Shape>),
+  Unsupported(&'static str),
+}
+
+impl Shape {
+  // Primitive types in the sense of XTypes, i.e. sequences and arrays of these
+  // do not get a DHEADER.
+  fn is_primitive(&self) -> bool {
+    matches!(self, Shape::Primitive | Shape::Enum)
+  }
+}
+
+// Deserializer, which feeds dummy values to the visitor and records the
+// data shape. Sequences are given one element, so that we can see the
+// element type.
+struct ShapeProbe<'a> {
+  shape: &'a mut Option<Shape>,
+}
+
+// Some types validate their strings, so give them something that
+// looks like a name.
+const PROBE_STR: &str = "x";
+
+macro_rules! probe_primitive {
+  ($method:ident, $visit:ident, $value:expr) => {
+    fn $method<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+      *self.shape = Some(Shape::Primitive);
+      visitor.$visit($value)
+    }
+  };
+}
+
+impl<'de, 'a> de::Deserializer<'de> for ShapeProbe<'a> {
+  type Error = Error;
+
+  fn deserialize_any<V: Visitor<'de>>(self, _visitor: V) -> Result<V::Value> {
+    Err(Error("CDR is not self-describing".to_string()))
+  }
+
+  probe_primitive!(deserialize_bool, visit_bool, false);
+  probe_primitive!(deserialize_i8, visit_i8, 0);
+  probe_primitive!(deserialize_i16, visit_i16, 0);
+  probe_primitive!(deserialize_i32, visit_i32, 0);
+  probe_primitive!(deserialize_i64, visit_i64, 0);
+  probe_primitive!(deserialize_u8, visit_u8, 0);
+  probe_primitive!(deserialize_u16, visit_u16, 0);
+  probe_primitive!(deserialize_u32, visit_u32, 0);
+  probe_primitive!(deserialize_u64, visit_u64, 0);
+  probe_primitive!(deserialize_f32, visit_f32, 0.0);
+  probe_primitive!(deserialize_f64, visit_f64, 0.0);
+  probe_primitive!(deserialize_char, visit_char, 'x');
+
+  fn deserialize_str<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+    *self.shape = Some(Shape::String);
+    visitor.visit_str(PROBE_STR)
+  }
+
+  fn deserialize_string<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+    self.deserialize_str(visitor)
+  }
+
+  fn deserialize_bytes<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+    *self.shape = Some(Shape::Sequence(Box::new(Shape::Primitive)));
+    visitor.visit_bytes(&[])
+  }
+
+  fn deserialize_byte_buf<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+    *self.shape = Some(Shape::Sequence(Box::new(Shape::Primitive)));
+    visitor.visit_byte_buf(Vec::new())
+  }
+
+  fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+    *self.shape = Some(Shape::Unsupported("Option"));
+    visitor.visit_none()
+  }
+
+  fn deserialize_unit<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+    *self.shape = Some(Shape::Unit);
+    visitor.visit_unit()
+  }
+
+  fn deserialize_unit_struct<V: Visitor<'de>>(
+    self,
+    _name: &'static str,
+    visitor: V,
+  ) -> Result<V::Value> {
+    self.deserialize_unit(visitor)
+  }
+
+  fn deserialize_newtype_struct<V: Visitor<'de>>(
+    self,
+    _name: &'static str,
+    visitor: V,
+  ) -> Result<V::Value> {
+    // transparent
+    visitor.visit_newtype_struct(self)
+  }
+
+  fn deserialize_seq<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+    let mut shapes = Vec::new();
+    let value = visitor.visit_seq(ProbeSeqAccess {
+      remaining: 1,
+      shapes: &mut shapes,
+    })?;
+    let element = shapes.pop().unwrap_or(Shape::Primitive);
+    *self.shape = Some(Shape::Sequence(Box::new(element)));
+    Ok(value)
+  }
+
+  fn deserialize_tuple<V: Visitor<'de>>(self, len: usize, visitor: V) -> Result<V::Value> {
+    let mut shapes = Vec::with_capacity(len);
+    let value = visitor.visit_seq(ProbeSeqAccess {
+      remaining: len,
+      shapes: &mut shapes,
+    })?;
+    *self.shape = Some(Shape::Array(shapes));
+    Ok(value)
+  }
+
+  fn deserialize_tuple_struct<V: Visitor<'de>>(
+    self,
+    _name: &'static str,
+    len: usize,
+    visitor: V,
+  ) -> Result<V::Value> {
+    let mut shapes = Vec::with_capacity(len);
+    let value = visitor.visit_seq(ProbeSeqAccess {
+      remaining: len,
+      shapes: &mut shapes,
+    })?;
+    *self.shape = Some(Shape::Struct(shapes));
+    Ok(value)
+  }
+
+  fn deserialize_map<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+    *self.shape = Some(Shape::Unsupported("map"));
+    visitor.visit_map(EmptyMapAccess)
+  }
+
+  fn deserialize_struct<V: Visitor<'de>>(
+    self,
+    name: &'static str,
+    fields: &'static [&'static str],
+    visitor: V,
+  ) -> Result<V::Value> {
+    self.deserialize_tuple_struct(name, fields.len(), visitor)
+  }
+
+  fn deserialize_enum<V: Visitor<'de>>(
+    self,
+    _name: &'static str,
+    _variants: &'static [&'static str],
+    visitor: V,
+  ) -> Result<V::Value> {
+    *self.shape = Some(Shape::Enum);
+    visitor.visit_enum(IndexEnumAccess { index: 0 })
+  }
+
+  fn deserialize_identifier<V: Visitor<'de>>(self, _visitor: V) -> Result<V::Value> {
+    Err(Error("Identifiers are not supported".to_string()))
+  }
+
+  fn deserialize_ignored_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+    self.deserialize_unit(visitor)
+  }
+}
+
+struct ProbeSeqAccess<'a> {
+  remaining: usize,
+  shapes: &'a mut Vec<Shape>,
+}
+
+impl<'de, 'a> SeqAccess<'de> for ProbeSeqAccess<'a> {
+  type Error = Error;
+
+  fn next_element_seed<T: DeserializeSeed<'de>>(&mut self, seed: T) -> Result<Option<T::Value>> {
+    if self.remaining == 0 {
+      return Ok(None);
+    }
+    self.remaining -= 1;
+    let mut shape = None;
+    let value = seed.deserialize(ShapeProbe { shape: &mut shape })?;
+    self.shapes.push(shape.unwrap_or(Shape::Unit));
+    Ok(Some(value))
+  }
+
+  fn size_hint(&self) -> Option<usize> {
+    Some(self.remaining)
+  }
+}
+
+struct EmptyMapAccess;
+
+impl<'de> MapAccess<'de> for EmptyMapAccess {
+  type Error = Error;
+
+  fn next_key_seed<K: DeserializeSeed<'de>>(&mut self, _seed: K) -> Result<Option<K::Value>> {
+    Ok(None)
+  }
+
+  fn next_value_seed<V: DeserializeSeed<'de>>(&mut self, _seed: V) -> Result<V::Value> {
+    Err(Error("Map has no values".to_string()))
+  }
+}
+
+// Enums are encoded as 32-bit discriminant, and only unit variants are
+// supported, same as in RustDDS CDR.
+struct IndexEnumAccess {
+  index: u32,
+}
+
+impl<'de> EnumAccess<'de> for IndexEnumAccess {
+  type Error = Error;
+  type Variant = Self;
+
+  fn variant_seed<V: DeserializeSeed<'de>>(self, seed: V) -> Result<(V::Value, Self)> {
+    let value = seed.deserialize(self.index.into_deserializer())?;
+    Ok((value, self))
+  }
+}
+
+impl<'de> VariantAccess<'de> for IndexEnumAccess {
+  type Error = Error;
+
+  fn unit_variant(self) -> Result<()> {
+    Ok(())
+  }
+
+  fn newtype_variant_seed<T: DeserializeSeed<'de>>(self, _seed: T) -> Result<T::Value> {
+    Err(Error(
+      "Enum variants with data are not supported".to_string(),
+    ))
+  }
+
+  fn tuple_variant<V: Visitor<'de>>(self, _len: usize, _visitor: V) -> Result<V::Value> {
+    Err(Error(
+      "Enum variants with data are not supported".to_string(),
+    ))
+  }
+
+  fn struct_variant<V: Visitor<'de>>(
+    self,
+    _fields: &'static [&'static str],
+    _visitor: V,
+  ) -> Result<V::Value> {
+    Err(Error(
+      "Enum variants with data are not supported".to_string(),
+    ))
+  }
+}
+
+// ---------------------------------------------------------------------------
+// Decoding
+
+struct Decoder<'i> {
+  input: &'i [u8],
+  pos: usize,
+  encoding: Xcdr2Encoding,
+}
+
+macro_rules! read_number {
+  ($method:ident, $t:ty) => {
+    fn $method(&mut self) -> Result<$t> {
+      const SIZE: usize = std::mem::size_of::<$t>();
+      self.align(SIZE.min(4))?;
+      let mut bytes = [0; SIZE];
+      bytes.copy_from_slice(self.read_bytes(SIZE)?);
+      Ok(if self.encoding.little_endian {
+        <$t>::from_le_bytes(bytes)
+      } else {
+        <$t>::from_be_bytes(bytes)
+      })
+    }
+  };
+}
+
+impl<'i> Decoder<'i> {
+  fn align(&mut self, alignment: usize) -> Result<()> {
+    let padding = (alignment - self.pos % alignment) % alignment;
+    self.read_bytes(padding).map(|_| ())
+  }
+
+  fn read_bytes(&mut self, count: usize) -> Result<&'i [u8]> {
+    let end = self
+      .pos
+      .checked_add(count)
+      .filter(|end| *end <= self.input.len())
+      .ok_or_else(|| {
+        Error(format!(
+          "Input too short: wanted {} bytes at offset {}, but length is {}",
+          count,
+          self.pos,
+          self.input.len()
+        ))
+      })?;
+    let bytes = &self.input[self.pos..end];
+    self.pos = end;
+    Ok(bytes)
+  }
+
+  read_number!(read_i8, i8);
+  read_number!(read_i16, i16);
+  read_number!(read_i32, i32);
+  read_number!(read_i64, i64);
+  read_number!(read_u8, u8);
+  read_number!(read_u16, u16);
+  read_number!(read_u32, u32);
+  read_number!(read_u64, u64);
+  read_number!(read_f32, f32);
+  read_number!(read_f64, f64);
+
+  fn read_length(&mut self) -> Result<usize> {
+    self.read_u32().map(|len| len as usize)
+  }
+
+  fn read_string(&mut self) -> Result<String> {
+    // length includes the terminating NUL
+    let len = self.read_length()?;
+    let bytes = self.read_bytes(len)?;
+    let bytes = bytes.strip_suffix(&[0]).unwrap_or(bytes);
+    String::from_utf8(bytes.to_vec()).map_err(|e| Error(format!("Bad UTF-8 in string: {}", e)))
+  }
+
+  // Reads a DHEADER and returns the position where the delimited data ends.
+  fn read_dheader(&mut self) -> Result<usize> {
+    let len = self.read_length()?;
+    Ok(self.pos.saturating_add(len))
+  }
+
+  fn skip_to(&mut self, end: usize) -> Result<()> {
+    if end > self.pos {
+      self.read_bytes(end - self.pos).map(|_| ())
+    } else {
+      Ok(())
+    }
+  }
+}
+
+struct ShapedDeserializer<'a, 'i> {
+  decoder: &'a mut Decoder<'i>,
+  shape: &'a Shape,
+}
+
+macro_rules! decode_primitive {
+  ($method:ident, $read:ident, $visit:ident) => {
+    fn $method<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+      visitor.$visit(self.decoder.$read()?)
+    }
+  };
+}
+
+impl<'de, 'a, 'i> de::Deserializer<'de> for ShapedDeserializer<'a, 'i> {
+  type Error = Error;
+
+  fn deserialize_any<V: Visitor<'de>>(self, _visitor: V) -> Result<V::Value> {
+    Err(Error("CDR is not self-describing".to_string()))
+  }
+
+  fn deserialize_bool<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+    visitor.visit_bool(self.decoder.read_u8()? != 0)
+  }
+
+  decode_primitive!(deserialize_i8, read_i8, visit_i8);
+  decode_primitive!(deserialize_i16, read_i16, visit_i16);
+  decode_primitive!(deserialize_i32, read_i32, visit_i32);
+  decode_primitive!(deserialize_i64, read_i64, visit_i64);
+  decode_primitive!(deserialize_u8, read_u8, visit_u8);
+  decode_primitive!(deserialize_u16, read_u16, visit_u16);
+  decode_primitive!(deserialize_u32, read_u32, visit_u32);
+  decode_primitive!(deserialize_u64, read_u64, visit_u64);
+  decode_primitive!(deserialize_f32, read_f32, visit_f32);
+  decode_primitive!(deserialize_f64, read_f64, visit_f64);
+
+  fn deserialize_char<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+    // CDR "char" is a single byte
+    visitor.visit_char(char::from(self.decoder.read_u8()?))
+  }
+
+  fn deserialize_str<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+    visitor.visit_string(self.decoder.read_string()?)
+  }
+
+  fn deserialize_string<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+    self.deserialize_str(visitor)
+  }
+
+  fn deserialize_bytes<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+    self.deserialize_byte_buf(visitor)
+  }
+
+  fn deserialize_byte_buf<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+    let len = self.decoder.read_length()?;
+    visitor.visit_byte_buf(self.decoder.read_bytes(len)?.to_vec())
+  }
+
+  fn deserialize_option<V: Visitor<'de>>(self, _visitor: V) -> Result<V::Value> {
+    Err(Error("Option is not supported".to_string()))
+  }
+
+  fn deserialize_unit<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+    visitor.visit_unit()
+  }
+
+  fn deserialize_unit_struct<V: Visitor<'de>>(
+    self,
+    _name: &'static str,
+    visitor: V,
+  ) -> Result<V::Value> {
+    visitor.visit_unit()
+  }
+
+  fn deserialize_newtype_struct<V: Visitor<'de>>(
+    self,
+    _name: &'static str,
+    visitor: V,
+  ) -> Result<V::Value> {
+    visitor.visit_newtype_struct(self)
+  }
+
+  fn deserialize_seq<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+    let element = match self.shape {
+      Shape::Sequence(element) => element.as_ref(),
+      other => {
+        return Err(Error(format!(
+          "Expected sequence, type shape is {:?}",
+          other
+        )))
+      }
+    };
+    let end = if element.is_primitive() {
+      None
+    } else {
+      Some(self.decoder.read_dheader()?)
+    };
+    let len = self.decoder.read_length()?;
+    let value = visitor.visit_seq(ShapedSeqAccess {
+      decoder: &mut *self.decoder,
+      shapes: std::slice::from_ref(element),
+      repeat: true,
+      index: 0,
+      len,
+    })?;
+    if let Some(end) = end {
+      self.decoder.skip_to(end)?;
+    }
+    Ok(value)
+  }
+
+  fn deserialize_tuple<V: Visitor<'de>>(self, len: usize, visitor: V) -> Result<V::Value> {
+    let elements = match self.shape {
+      Shape::Array(elements) if elements.len() == len => elements,
+      other => return Err(Error(format!("Expected array, type shape is {:?}", other))),
+    };
+    let end = if elements.iter().all(Shape::is_primitive) {
+      None
+    } else {
+      Some(self.decoder.read_dheader()?)
+    };
+    let value = visitor.visit_seq(ShapedSeqAccess {
+      decoder: &mut *self.decoder,
+      shapes: elements,
+      repeat: false,
+      index: 0,
+      len,
+    })?;
+    if let Some(end) = end {
+      self.decoder.skip_to(end)?;
+    }
+    Ok(value)
+  }
+
+  fn deserialize_tuple_struct<V: Visitor<'de>>(
+    self,
+    _name: &'static str,
+    len: usize,
+    visitor: V,
+  ) -> Result<V::Value> {
+    let fields = match self.shape {
+      Shape::Struct(fields) if fields.len() == len => fields,
+      other => return Err(Error(format!("Expected struct, type shape is {:?}", other))),
+    };
+    let end = if self.decoder.encoding.delimited {
+      Some(self.decoder.read_dheader()?)
+    } else {
+      None
+    };
+    let value = visitor.visit_seq(ShapedSeqAccess {
+      decoder: &mut *self.decoder,
+      shapes: fields,
+      repeat: false,
+      index: 0,
+      len,
+    })?;
+    // Appendable types may have been extended with fields unknown to us.
+    // DHEADER tells how much to skip.
+    if let Some(end) = end {
+      self.decoder.skip_to(end)?;
+    }
+    Ok(value)
+  }
+
+  fn deserialize_map<V: Visitor<'de>>(self, _visitor: V) -> Result<V::Value> {
+    Err(Error("Map is not supported".to_string()))
+  }
+
+  fn deserialize_struct<V: Visitor<'de>>(
+    self,
+    name: &'static str,
+    fields: &'static [&'static str],
+    visitor: V,
+  ) -> Result<V::Value> {
+    self.deserialize_tuple_struct(name, fields.len(), visitor)
+  }
+
+  fn deserialize_enum<V: Visitor<'de>>(
+    self,
+    _name: &'static str,
+    _variants: &'static [&'static str],
+    visitor: V,
+  ) -> Result<V::Value> {
+    let index = self.decoder.read_u32()?;
+    visitor.visit_enum(IndexEnumAccess { index })
+  }
+
+  fn deserialize_identifier<V: Visitor<'de>>(self, _visitor: V) -> Result<V::Value> {
+    Err(Error("Identifiers are not supported".to_string()))
+  }
+
+  fn deserialize_ignored_any<V: Visitor<'de>>(self, _visitor: V) -> Result<V::Value> {
+    Err(Error(
+      "Cannot skip data without knowing its type".to_string(),
+    ))
+  }
+}
+
+struct ShapedSeqAccess<'a, 'i> {
+  decoder: &'a mut Decoder<'i>,
+  shapes: &'a [Shape],
+  repeat: bool, // true = all elements have shapes[0], false = each has its own
+  index: usize,
+  len: usize,
+}
+
+impl<'de, 'a, 'i> SeqAccess<'de> for ShapedSeqAccess<'a, 'i> {
+  type Error = Error;
+
+  fn next_element_seed<T: DeserializeSeed<'de>>(&mut self, seed: T) -> Result<Option<T::Value>> {
+    if self.index >= self.len {
+      return Ok(None);
+    }
+    let shape = if self.repeat {
+      &self.shapes[0]
+    } else {
+      &self.shapes[self.index]
+    };
+    self.index += 1;
+    seed
+      .deserialize(ShapedDeserializer {
+        decoder: &mut *self.decoder,
+        shape,
+      })
+      .map(Some)
+  }
+
+  fn size_hint(&self) -> Option<usize> {
+    Some(self.len - self.index)
+  }
+}
+
+// -------------------------------------------------------------------------------------
+
+#[cfg(test)]
+#[derive(Debug, PartialEq, serde::Deserialize)]
+struct TestInner {
+  x: i16,
+  y: f64,
+}
+
+#[cfg(test)]
+#[derive(Debug, PartialEq, serde::Deserialize)]
+struct TestOuter {
+  a: u8,
+  b: f64,
+  names: Vec<String>,
+  numbers: Vec<u16>,
+  inners: Vec<TestInner>,
+}
+
+#[test]
+fn xcdr2_decode_final() {
+  let mut b = vec![7u8, 0, 0, 0]; // a + padding to 4, not 8
+  b.extend(2.5f64.to_le_bytes());
+  // names: DHEADER, length, string "hi"
+  b.extend(10u32.to_le_bytes());
+  b.extend(1u32.to_le_bytes());
+  b.extend(3u32.to_le_bytes());
+  b.extend(b"hi\0\0"); // incl. padding
+                       // numbers: no DHEADER for primitives
+  b.extend(2u32.to_le_bytes());
+  b.extend(1u16.to_le_bytes());
+  b.extend(2u16.to_le_bytes());
+  // inners: DHEADER, length, elements
+  b.extend(16u32.to_le_bytes());
+  b.extend(1u32.to_le_bytes());
+  b.extend((-3i16).to_le_bytes());
+  b.extend([0, 0]);
+  b.extend(0.5f64.to_le_bytes());
+
+  let encoding = Xcdr2Encoding::from_representation(*CDR2_LE).unwrap();
+  let decoded: TestOuter = from_bytes(&b, encoding).unwrap();
+  assert_eq!(
+    decoded,
+    TestOuter {
+      a: 7,
+      b: 2.5,
+      names: vec!["hi".to_string()],
+      numbers: vec![1, 2],
+      inners: vec![TestInner { x: -3, y: 0.5 }],
+    }
+  );
+}
+
+#[test]
+fn xcdr2_decode_delimited_skips_unknown_fields() {
+  // DHEADER says 16 bytes, but TestInner only knows 12 of them.
+  let mut b = 16u32.to_be_bytes().to_vec();
+  b.extend(5i16.to_be_bytes());
+  b.extend([0, 0]);
+  b.extend(1.0f64.to_be_bytes());
+  b.extend(99u32.to_be_bytes()); // appended field unknown to us
+
+  let encoding = Xcdr2Encoding::from_representation(*D_CDR2_BE).unwrap();
+  let decoded: TestInner = from_bytes(&b, encoding).unwrap();
+  assert_eq!(decoded, TestInner { x: 5, y: 1.0 });
+}
+
+#[test]
+fn xcdr2_decode_too_short() {
+  let encoding = Xcdr2Encoding::from_representation(*CDR2_LE).unwrap();
+  assert!(from_bytes::<TestInner>(&[1, 0], encoding).is_err());
+}
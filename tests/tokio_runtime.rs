@@ -0,0 +1,41 @@
+//! Check that ros2-client works on a Tokio runtime without smol.
+//!
+//! Run with `cargo test --no-default-features --features rt-tokio`
+#![cfg(feature = "rt-tokio")]
+
+use std::time::Duration;
+
+use ros2_client::*;
+
+#[tokio::test(flavor = "multi_thread")]
+async fn spin_and_timeout_on_tokio() {
+  let context = Context::new().unwrap();
+  let mut node = context
+    .new_node(
+      NodeName::new("/tokio_test", "tokio_node").unwrap(),
+      NodeOptions::new().enable_rosout(false),
+    )
+    .unwrap();
+  let spinner = tokio::spawn(node.spinner().unwrap().spin());
+
+  let qos = rustdds::QosPolicyBuilder::new().build();
+  let client = node
+    .create_client::<AService<i64, i64>>(
+      ServiceMapping::Enhanced,
+      &Name::new("/tokio_test", "nobody_home").unwrap(),
+      &ServiceTypeName::new("example_interfaces", "Nothing"),
+      qos.clone(),
+      qos,
+    )
+    .unwrap();
+
+  // There is no Server, so this must time out using the Tokio timer.
+  let response = client
+    .async_call_service_timeout(1, Duration::from_millis(200))
+    .await
+    .unwrap();
+  assert_eq!(response, None);
+
+  drop(node); // stops the Spinner
+  spinner.await.unwrap().unwrap();
+}